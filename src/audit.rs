@@ -0,0 +1,152 @@
+//! Checking the toolchains repositories are pinned to against a policy's age
+//! budgets.
+
+use crate::{
+    get_rust_version,
+    policy::{self, Budget},
+    toolchain_file::{self, parse_version, Pin},
+    Fetcher,
+    Session,
+};
+use anyhow::{bail, Result};
+use chrono::{NaiveDate, Utc};
+use std::{collections::HashMap, path::Path};
+
+enum Verdict {
+    Ok(String),
+    Skipped(String),
+    Violation(String),
+}
+
+/// Number of minor releases `pinned` is behind `latest`, if they share a
+/// major version.
+fn releases_behind(pinned: &str, latest: &str) -> Option<u64> {
+    let pinned = parse_version(pinned);
+    let latest = parse_version(latest);
+    match (pinned.get(..2), latest.get(..2)) {
+        (
+            Some(&[pinned_major, pinned_minor]),
+            Some(&[latest_major, latest_minor]),
+        ) if pinned_major == latest_major => {
+            Some(latest_minor.saturating_sub(pinned_minor))
+        },
+        _ => None,
+    }
+}
+
+struct Auditor<'a> {
+    fetcher: &'a Fetcher,
+    budgets: &'a HashMap<String, Budget>,
+    today: NaiveDate,
+    latest_versions: HashMap<String, Option<String>>,
+}
+
+impl Auditor<'_> {
+    fn latest_version(&mut self, channel: &str) -> Result<Option<String>> {
+        if let Some(version) = self.latest_versions.get(channel) {
+            return Ok(version.clone());
+        }
+        let manifest = self
+            .fetcher
+            .manifest(&self.fetcher.options().manifest_url(channel))?;
+        let version = manifest.as_ref().and_then(get_rust_version);
+        self.latest_versions
+            .insert(channel.to_owned(), version.clone());
+        Ok(version)
+    }
+
+    fn audit(&mut self, repository: &Path) -> Result<Verdict> {
+        let name = match toolchain_file::find(repository) {
+            Some(path) => toolchain_file::read_channel(&path)?,
+            None => {
+                return Ok(Verdict::Skipped("no toolchain file".to_owned()))
+            },
+        };
+        let name = match name {
+            Some(name) => name,
+            None => {
+                return Ok(Verdict::Skipped("no channel pinned".to_owned()))
+            },
+        };
+        let pin = match Pin::parse(&name) {
+            Some(pin) => pin,
+            None => {
+                return Ok(Verdict::Skipped(format!(
+                    "{} is not pinned to a specific build",
+                    name
+                )))
+            },
+        };
+        let budget = match self.budgets.get(&pin.channel) {
+            Some(budget) => budget,
+            None => return Ok(Verdict::Ok(name)),
+        };
+        let Budget {
+            max_age_days,
+            max_releases_behind,
+        } = *budget;
+
+        if let (Some(date), Some(max_age_days)) = (pin.date, max_age_days) {
+            let age = self.today.signed_duration_since(date).num_days();
+            if age > i64::from(max_age_days) {
+                return Ok(Verdict::Violation(format!(
+                    "{} is {} days old, budget is {}",
+                    name, age, max_age_days
+                )));
+            }
+        }
+        if let (Some(version), Some(max_releases_behind)) =
+            (&pin.version, max_releases_behind)
+        {
+            if let Some(latest) = self.latest_version(&pin.channel)? {
+                if let Some(behind) = releases_behind(version, &latest) {
+                    if behind > u64::from(max_releases_behind) {
+                        return Ok(Verdict::Violation(format!(
+                            "{} is {} releases behind {}, budget is {}",
+                            name, behind, latest, max_releases_behind
+                        )));
+                    }
+                }
+            }
+        }
+        Ok(Verdict::Ok(name))
+    }
+}
+
+pub fn run(
+    session: &Session,
+    policy_location: &str,
+    policy_key: Option<&str>,
+    repositories: &[impl AsRef<Path>],
+) -> Result<()> {
+    let fetcher = &session.fetcher;
+    let policy = policy::load(fetcher.client(), policy_location, policy_key)?;
+    let mut auditor = Auditor {
+        fetcher,
+        budgets: &policy.budget,
+        today: Utc::today().naive_utc(),
+        latest_versions: HashMap::new(),
+    };
+
+    let mut violations = 0;
+    for repository in repositories {
+        let repository = repository.as_ref();
+        match auditor.audit(repository)? {
+            Verdict::Ok(name) => {
+                println!("{}: ok ({})", repository.display(), name)
+            },
+            Verdict::Skipped(reason) => {
+                println!("{}: skipped, {}", repository.display(), reason)
+            },
+            Verdict::Violation(message) => {
+                violations += 1;
+                println!("{}: {}", repository.display(), message);
+            },
+        }
+    }
+
+    if violations > 0 {
+        bail!("{} repositories violate the toolchain budget", violations);
+    }
+    Ok(())
+}
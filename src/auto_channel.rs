@@ -0,0 +1,62 @@
+//! Picking the release channel a project needs for `--channel auto`.
+//!
+//! A channel named by the project's toolchain file wins. Otherwise projects
+//! enabling unstable features with `#![feature(...)]` get nightly and
+//! everything else gets stable. A `rust-version` in the manifest always names
+//! a stable release, so it isn't consulted.
+
+use crate::{
+    find_files,
+    toolchain_file::{self, Pin},
+};
+use anyhow::{Context, Result};
+use std::path::Path;
+
+static CHANNELS: &[&str] = &["stable", "beta", "nightly"];
+
+/// The channel of the toolchain the project's toolchain file pins, if it
+/// names one.
+fn pinned_channel(dir: &Path) -> Result<Option<String>> {
+    let name = match toolchain_file::find(dir) {
+        Some(path) => toolchain_file::read_channel(&path)?,
+        None => None,
+    };
+    let name = match name {
+        Some(name) => name,
+        None => return Ok(None),
+    };
+    let channel = match Pin::parse(&name) {
+        Some(pin) => pin.channel,
+        None => name.split('-').next().unwrap_or_default().to_owned(),
+    };
+    Ok(Some(channel).filter(|channel| CHANNELS.contains(&channel.as_str())))
+}
+
+/// Whether any source file of the project at `root` enables unstable
+/// features.
+fn uses_unstable_features(root: &Path) -> Result<bool> {
+    let mut sources = Vec::new();
+    find_files(root, &|name| name.ends_with(".rs"), &mut sources)?;
+    for path in sources {
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("error reading {}", path.display()))?;
+        if content.contains("#![feature(") {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+pub fn detect(dir: &Path) -> Result<String> {
+    if let Some(channel) = pinned_channel(dir)? {
+        return Ok(channel);
+    }
+    let root = dir
+        .ancestors()
+        .find(|dir| dir.join("Cargo.toml").is_file())
+        .unwrap_or(dir);
+    if uses_unstable_features(root)? {
+        return Ok("nightly".to_owned());
+    }
+    Ok("stable".to_owned())
+}
@@ -0,0 +1,52 @@
+//! Builds recorded as bad after they were selected, like ones that
+//! miscompile or crash, so later resolutions pass over them.
+
+use crate::{get_rust_version, make_toolchain_name, Dirs, Manifest, Session};
+use anyhow::{Context, Result};
+use std::{collections::BTreeMap, path::PathBuf};
+
+/// Reasons by toolchain name, like `nightly-2024-06-02` or `1.79.0`.
+pub type BadBuilds = BTreeMap<String, String>;
+
+fn path(dirs: &Dirs) -> Result<PathBuf> {
+    Ok(dirs.state()?.join("bad-builds.toml"))
+}
+
+pub fn load(dirs: &Dirs) -> Result<BadBuilds> {
+    let path = path(dirs)?;
+    match std::fs::read_to_string(&path) {
+        Ok(content) => toml::from_str(&content)
+            .with_context(|| format!("error parsing {}", path.display())),
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+            Ok(BadBuilds::new())
+        },
+        Err(error) => Err(error)
+            .with_context(|| format!("error reading {}", path.display())),
+    }
+}
+
+pub fn mark(session: &Session, name: &str, reason: &str) -> Result<()> {
+    let mut bad_builds = load(&session.dirs)?;
+    bad_builds.insert(name.to_owned(), reason.to_owned());
+    session.write_file(&path(&session.dirs)?, toml::to_string(&bad_builds)?)
+}
+
+/// Why the build of `channel` was marked bad, if it was, by its dated name,
+/// or by its version for stable builds. Nightly and beta builds share the
+/// version of a stable release without being it.
+pub fn reason<'a>(
+    bad_builds: &'a BadBuilds,
+    channel: &str,
+    manifest: &Manifest,
+) -> Option<&'a str> {
+    let dated = make_toolchain_name(manifest, channel, true);
+    bad_builds
+        .get(&dated)
+        .or_else(|| {
+            if channel != "stable" {
+                return None;
+            }
+            bad_builds.get(&get_rust_version(manifest)?)
+        })
+        .map(String::as_str)
+}
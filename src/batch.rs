@@ -0,0 +1,58 @@
+//! Resolving a stream of queries read from stdin, so other tools can drive
+//! this one as a coprocess.
+//!
+//! Each line is a query given either as whitespace-separated arguments or as a
+//! JSON array of arguments. Each query gets exactly one line of output: the
+//! resolved toolchain, or `error: ` followed by the reason it failed.
+
+use crate::{resolve, Query, Session};
+use anyhow::{Context, Result};
+use clap::Parser;
+use std::io::{BufRead, Write};
+
+fn parse_args(line: &str) -> Result<Vec<String>> {
+    if line.starts_with('[') {
+        return serde_json::from_str(line).context("error parsing query");
+    }
+    Ok(line.split_whitespace().map(str::to_owned).collect())
+}
+
+fn resolve_line(session: &Session, line: &str) -> Result<String> {
+    let args = parse_args(line)?;
+    let mut query = Query::try_parse_from(
+        std::iter::once("rust-latest".to_owned()).chain(args),
+    )?;
+    query.detect_project()?;
+    resolve(session, &query)
+}
+
+/// Answers the queries on stdin with the fetcher of `session`, shared between
+/// them so each dated manifest is downloaded once.
+pub fn run(session: &Session) -> Result<()> {
+    let stdin = std::io::stdin();
+    let stdout = std::io::stdout();
+    let mut stdout = stdout.lock();
+    for line in stdin.lock().lines() {
+        let line = line.context("error reading query")?;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        // Each query sees the channels as they are now.
+        session.fetcher.forget_latest();
+        let result = resolve_line(session, line);
+        session.report_diagnostics();
+        let written = match result {
+            Ok(toolchain) => writeln!(stdout, "{}", toolchain),
+            Err(error) => writeln!(
+                stdout,
+                "error: {}",
+                format!("{:#}", error).replace('\n', " ")
+            ),
+        };
+        written
+            .and_then(|()| stdout.flush())
+            .context("error writing result")?;
+    }
+    Ok(())
+}
@@ -0,0 +1,379 @@
+//! Summarizing what bumping a dated pin to a newer build of its channel would
+//! gain and lose in component availability, and bumping every pin in a
+//! project at once.
+
+use crate::{
+    find_files,
+    find_viable_manifest,
+    get_rust_version,
+    has_components,
+    make_toolchain_name,
+    manifests_in,
+    toolchain_file::{self, Pin},
+    toolchain_manifest_url,
+    After,
+    Manifest,
+    Query,
+    Requirements,
+    Session,
+    TargetsOpt,
+    CURRENT_TARGET,
+};
+use anyhow::{bail, Context, Result};
+use std::{
+    collections::BTreeSet,
+    path::{Path, PathBuf},
+};
+
+fn is_available(
+    manifest: &Manifest,
+    component: &str,
+    targets: &[&str],
+) -> bool {
+    has_components(manifest, &[component.to_owned()], targets)
+}
+
+pub fn run(session: &Session, dir: &Path, targets: TargetsOpt) -> Result<()> {
+    let targets = targets
+        .triples(session.tier_list.as_deref())?
+        .unwrap_or_else(|| vec![CURRENT_TARGET.to_owned()]);
+    let targets = &targets.iter().map(String::as_str).collect::<Vec<_>>();
+    let path = match toolchain_file::find(dir) {
+        Some(path) => path,
+        None => bail!("no toolchain file found for {}", dir.display()),
+    };
+    let name = match toolchain_file::read_channel(&path)? {
+        Some(name) => name,
+        None => bail!("{} doesn't pin a channel", path.display()),
+    };
+    let (channel, date) = match Pin::parse(&name) {
+        Some(Pin {
+            channel,
+            date: Some(date),
+            ..
+        }) => (channel, date),
+        _ => bail!("{} is not a dated toolchain like nightly-2024-02-03", name),
+    };
+
+    let fetcher = &session.fetcher;
+    let pinned = fetcher
+        .manifest(&toolchain_manifest_url(fetcher.options(), &name))?
+        .with_context(|| format!("no manifest found for toolchain {}", name))?;
+    let newer = manifests_in(fetcher, &channel, &After(date), &[])?
+        .filter(|manifest| {
+            manifest
+                .as_ref()
+                .map_or(true, |manifest| manifest.date > date)
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let latest = match newer.first() {
+        Some(latest) => latest,
+        None => {
+            println!("{} is the latest {} build", name, channel);
+            return Ok(());
+        },
+    };
+
+    let components = pinned
+        .packages
+        .keys()
+        .chain(latest.packages.keys())
+        .map(String::as_str)
+        .collect::<BTreeSet<_>>();
+    let (broken, working): (Vec<_>, Vec<_>) = components
+        .into_iter()
+        .partition(|component| !is_available(&pinned, component, targets));
+    let toolchain_name =
+        |manifest: &Manifest| make_toolchain_name(manifest, &channel, true);
+
+    println!("pinned: {}", name);
+    println!("latest: {}", toolchain_name(latest));
+
+    let gained = broken
+        .iter()
+        .filter_map(|&component| {
+            let since = newer
+                .iter()
+                .rev()
+                .find(|manifest| is_available(manifest, component, targets))?;
+            Some((component, since))
+        })
+        .collect::<Vec<_>>();
+    if !gained.is_empty() {
+        println!("available in newer builds:");
+        for (component, since) in &gained {
+            let note = if is_available(latest, component, targets) {
+                ""
+            } else {
+                ", missing again in latest"
+            };
+            println!(
+                "  {}: since {}{}",
+                component,
+                toolchain_name(since),
+                note
+            );
+        }
+    }
+
+    let lost = working
+        .iter()
+        .copied()
+        .filter(|component| !is_available(latest, component, targets))
+        .collect::<Vec<_>>();
+    if !lost.is_empty() {
+        println!("missing in latest:");
+        for component in &lost {
+            println!("  {}", component);
+        }
+    }
+
+    let newest_without_losses = newer.iter().find(|manifest| {
+        working
+            .iter()
+            .all(|component| is_available(manifest, component, targets))
+    });
+    match newest_without_losses {
+        Some(manifest) => println!(
+            "newest build without missing components: {}",
+            toolchain_name(manifest)
+        ),
+        None => println!("every newer build is missing some components"),
+    }
+    Ok(())
+}
+
+/// Whether a file can pin a toolchain besides the toolchain file, like
+/// `Dockerfile`, `ci.Dockerfile` or `Dockerfile.release`.
+fn is_dockerfile(name: &str) -> bool {
+    name == "Dockerfile"
+        || name.starts_with("Dockerfile.")
+        || name.ends_with(".Dockerfile")
+}
+
+/// The files under `dir` that can pin the toolchain: the toolchain file,
+/// Dockerfiles and GitHub Actions workflows.
+fn pinning_files(dir: &Path, toolchain_file: &Path) -> Result<Vec<PathBuf>> {
+    let mut paths = vec![toolchain_file.to_owned()];
+    find_files(dir, &is_dockerfile, &mut paths)?;
+    // Hidden directories aren't searched, so workflows are looked up directly.
+    let workflows = dir.join(".github/workflows");
+    if workflows.is_dir() {
+        find_files(
+            &workflows,
+            &|name| name.ends_with(".yml") || name.ends_with(".yaml"),
+            &mut paths,
+        )?;
+    }
+    paths.sort();
+    paths.dedup();
+    Ok(paths)
+}
+
+/// Replaces `old` with `new` where it appears as a toolchain name, keeping
+/// suffixes like the `-slim` of `rust:1.79.0-slim` or the host of
+/// `nightly-2024-05-20-x86_64-unknown-linux-gnu`, but not where it's part of
+/// a longer version like `1.79.0.1` or `11.79.0`.
+fn replace_pin(content: &str, old: &str, new: &str) -> String {
+    let is_name_char = |c: char| c.is_alphanumeric() || matches!(c, '_' | '.');
+    let mut replaced = String::with_capacity(content.len());
+    let mut rest = 0;
+    for (start, _) in content.match_indices(old) {
+        let end = start + old.len();
+        let before = content[..start].chars().next_back();
+        let after = content[end..].chars().next();
+        if before.map_or(false, |c| is_name_char(c) || c == '-')
+            || after.map_or(false, is_name_char)
+        {
+            continue;
+        }
+        replaced.push_str(&content[rest..start]);
+        replaced.push_str(new);
+        rest = end;
+    }
+    replaced.push_str(&content[rest..]);
+    replaced
+}
+
+/// Prints a unified diff of a file whose lines were changed in place, which
+/// replacing pins always does.
+fn print_diff(path: &Path, old: &str, new: &str) {
+    println!("--- a/{}", path.display());
+    println!("+++ b/{}", path.display());
+    for (number, (old, new)) in old.lines().zip(new.lines()).enumerate() {
+        if old != new {
+            println!("@@ -{0},1 +{0},1 @@", number + 1);
+            println!("-{}", old);
+            println!("+{}", new);
+        }
+    }
+}
+
+/// The components that became available and went missing on `targets`
+/// between `pinned` and `latest`.
+fn component_changes<'a>(
+    pinned: &'a Manifest,
+    latest: &'a Manifest,
+    targets: &[&str],
+) -> (Vec<&'a str>, Vec<&'a str>) {
+    let components = pinned
+        .packages
+        .keys()
+        .chain(latest.packages.keys())
+        .map(String::as_str)
+        .collect::<BTreeSet<_>>();
+    let gained = components
+        .iter()
+        .copied()
+        .filter(|component| {
+            !is_available(pinned, component, targets)
+                && is_available(latest, component, targets)
+        })
+        .collect();
+    let lost = components
+        .iter()
+        .copied()
+        .filter(|component| {
+            is_available(pinned, component, targets)
+                && !is_available(latest, component, targets)
+        })
+        .collect();
+    (gained, lost)
+}
+
+/// Bumps every pin of the project's toolchain under `dir` to the latest build
+/// viable for `query` and what the toolchain file asks for, printing the
+/// diff and a commit message for it. Nothing is written unless `write` is
+/// set.
+pub fn changeset(
+    session: &Session,
+    dir: &Path,
+    mut query: Query,
+    write: bool,
+) -> Result<()> {
+    let path = match toolchain_file::find(dir) {
+        Some(path) => path,
+        None => bail!("no toolchain file found for {}", dir.display()),
+    };
+    let name = match toolchain_file::read_channel(&path)? {
+        Some(name) => name,
+        None => bail!("{} doesn't pin a channel", path.display()),
+    };
+    let pin = match Pin::parse(&name) {
+        Some(pin) => pin,
+        None => bail!("{} is not pinned to a specific build", name),
+    };
+    query.from_toolchain_file = Some(Some(path.clone()));
+    query.detect_project()?;
+
+    let latest = find_viable_manifest(session, &query)?;
+    let latest_name =
+        make_toolchain_name(&latest, &pin.channel, pin.date.is_some());
+    if latest_name == name {
+        println!("{} is the latest viable {} build", name, pin.channel);
+        return Ok(());
+    }
+    // Newer builds than the latest viable one may lack something the
+    // project needs, and going back to it would be a downgrade.
+    if !pin.is_behind(&latest) {
+        println!(
+            "{} is up to date, the latest viable {} build is {}",
+            name, pin.channel, latest_name
+        );
+        return Ok(());
+    }
+
+    let mut changes = Vec::new();
+    for path in pinning_files(dir, &path)? {
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("error reading {}", path.display()))?;
+        let bumped = replace_pin(&content, &name, &latest_name);
+        if bumped != content {
+            changes.push((path, content, bumped));
+        }
+    }
+    for (path, content, bumped) in &changes {
+        let path = path.strip_prefix(dir).unwrap_or(path);
+        print_diff(path, content, bumped);
+    }
+
+    println!();
+    println!("Bump Rust toolchain from {} to {}", name, latest_name);
+    let mut notes = Vec::new();
+    if pin.version.is_none() {
+        if let Some(version) = get_rust_version(&latest) {
+            notes.push(format!("rustc {}", version));
+        }
+    }
+    let fetcher = &session.fetcher;
+    if let Some(pinned) =
+        fetcher.manifest(&toolchain_manifest_url(fetcher.options(), &name))?
+    {
+        let requirements = Requirements::new(session, &query)?;
+        let (gained, lost) =
+            component_changes(&pinned, &latest, &requirements.targets());
+        if !gained.is_empty() {
+            notes.push(format!("now available: {}", gained.join(", ")));
+        }
+        if !lost.is_empty() {
+            notes.push(format!("no longer available: {}", lost.join(", ")));
+        }
+    }
+    if !notes.is_empty() {
+        println!();
+        for note in notes {
+            println!("- {}", note);
+        }
+    }
+
+    if write {
+        for (path, _, bumped) in &changes {
+            session.write_file(path, bumped)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pins_are_replaced_as_whole_names() {
+        let content = "channel = \"1.79.0\"\nFROM rust:1.79.0\n";
+        assert_eq!(
+            replace_pin(content, "1.79.0", "1.80.1"),
+            "channel = \"1.80.1\"\nFROM rust:1.80.1\n"
+        );
+    }
+
+    #[test]
+    fn suffixed_docker_tags_are_replaced() {
+        assert_eq!(
+            replace_pin("FROM rust:1.79.0-slim AS build", "1.79.0", "1.80.1"),
+            "FROM rust:1.80.1-slim AS build"
+        );
+        assert_eq!(
+            replace_pin(
+                "toolchain: nightly-2024-05-20-x86_64-unknown-linux-gnu",
+                "nightly-2024-05-20",
+                "nightly-2024-06-01"
+            ),
+            "toolchain: nightly-2024-06-01-x86_64-unknown-linux-gnu"
+        );
+    }
+
+    #[test]
+    fn longer_versions_are_left_alone() {
+        let content = "1.79.0.1 11.79.0 v1.79.0 rust-1.79.0";
+        assert_eq!(replace_pin(content, "1.79.0", "1.80.1"), content);
+    }
+
+    #[test]
+    fn adjacent_pins_are_all_replaced() {
+        assert_eq!(
+            replace_pin("1.79.0 1.79.0,1.79.0", "1.79.0", "1.80.1"),
+            "1.80.1 1.80.1,1.80.1"
+        );
+    }
+}
@@ -0,0 +1,128 @@
+//! Keeping downloaded manifests on disk. A dated manifest never changes once
+//! published, so it only has to be downloaded once. The latest manifest of a
+//! channel moves on, so its cached copy is used as is only with `--offline`,
+//! and otherwise revalidated with the server.
+
+use crate::{dated_manifest_url, http::Validators, write_file, Options};
+use anyhow::{Context, Result};
+use directories::ProjectDirs;
+use sha2::{Digest, Sha256};
+use std::path::PathBuf;
+
+/// The cache file for `url` and whether it's of a dated manifest. Each dist
+/// server gets its own directory, so manifests of a fork never mix with the
+/// official ones.
+fn path(options: &Options, url: &str) -> Option<(PathBuf, bool)> {
+    let base_url = options.base_url();
+    let (name, dated) = match dated_manifest_url(&base_url, url) {
+        Some((date, channel)) => (format!("{}-{}.toml", channel, date), true),
+        None => {
+            let channel = url
+                .strip_prefix(base_url.as_str())?
+                .strip_prefix("/channel-rust-")?
+                .strip_suffix(".toml")?;
+            (format!("{}.toml", channel), false)
+        },
+    };
+    let server = format!("{:x}", Sha256::digest(base_url.as_bytes()));
+    let dir = match &options.cache_dir {
+        Some(dir) => dir.clone(),
+        None => ProjectDirs::from("", "", "rust-latest")?
+            .cache_dir()
+            .to_owned(),
+    };
+    Some((dir.join("manifests").join(&server[..16]).join(name), dated))
+}
+
+/// The manifest at `url` as downloaded before, if it can be trusted to be
+/// current.
+pub fn read(options: &Options, url: &str) -> Result<Option<Vec<u8>>> {
+    let path = match path(options, url) {
+        Some((path, dated)) if dated || options.http.offline => path,
+        _ => return Ok(None),
+    };
+    match std::fs::read(&path) {
+        Ok(content) => Ok(Some(content)),
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(error) => Err(error)
+            .with_context(|| format!("error reading {}", path.display())),
+    }
+}
+
+/// The cached copy of the latest manifest at `url` along with the validators
+/// to ask the server whether it's still current.
+pub fn revalidation(
+    options: &Options,
+    url: &str,
+) -> Result<Option<(Vec<u8>, Validators)>> {
+    let path = match path(options, url) {
+        Some((path, false)) => path,
+        _ => return Ok(None),
+    };
+    let validators_path = path.with_extension("validators.toml");
+    let validators = match std::fs::read_to_string(&validators_path) {
+        Ok(content) => match toml::from_str(&content) {
+            Ok(validators) => validators,
+            Err(_) => return Ok(None),
+        },
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+            return Ok(None)
+        },
+        Err(error) => {
+            return Err(error).with_context(|| {
+                format!("error reading {}", validators_path.display())
+            })
+        },
+    };
+    match std::fs::read(&path) {
+        Ok(content) => Ok(Some((content, validators))),
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(error) => Err(error)
+            .with_context(|| format!("error reading {}", path.display())),
+    }
+}
+
+/// The file recording that the dated manifest at `url` doesn't exist.
+fn missing_path(options: &Options, url: &str) -> Option<PathBuf> {
+    match path(options, url)? {
+        (path, true) => Some(path.with_extension("missing")),
+        (_, false) => None,
+    }
+}
+
+/// Whether the dated manifest at `url` was found not to exist before, as on
+/// days without a release.
+pub fn is_missing(options: &Options, url: &str) -> bool {
+    missing_path(options, url).map_or(false, |path| path.is_file())
+}
+
+/// Records that the dated manifest at `url` doesn't exist, so it can be told
+/// apart from one that was never downloaded when offline.
+pub fn write_missing(options: &Options, url: &str) -> Result<()> {
+    match missing_path(options, url) {
+        Some(path) if !options.read_only => write_file(&path, ""),
+        _ => Ok(()),
+    }
+}
+
+/// Keeps the downloaded `content` of `url` if it's a manifest, along with the
+/// `validators` the server sent for the latest manifests.
+pub fn write(
+    options: &Options,
+    url: &str,
+    content: &[u8],
+    validators: &Validators,
+) -> Result<()> {
+    let (path, dated) = match path(options, url) {
+        Some(path) if !options.read_only => path,
+        _ => return Ok(()),
+    };
+    write_file(&path, content)?;
+    if !dated {
+        write_file(
+            &path.with_extension("validators.toml"),
+            toml::to_string(validators)?,
+        )?;
+    }
+    Ok(())
+}
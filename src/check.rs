@@ -0,0 +1,44 @@
+//! Checking whether one toolchain has everything a query requires, without
+//! searching for the latest.
+
+use crate::{
+    bad_builds,
+    toolchain_file::Pin,
+    toolchain_manifest_url,
+    Query,
+    Requirements,
+    Session,
+};
+use anyhow::{bail, Context, Result};
+
+pub fn run(session: &Session, name: &str, query: &mut Query) -> Result<()> {
+    query.channel = match Pin::parse(name) {
+        Some(pin) => pin.channel,
+        None => name.to_owned(),
+    };
+    let requirements = Requirements::new(session, query)?;
+    let fetcher = &session.fetcher;
+    let manifest = fetcher
+        .manifest(&toolchain_manifest_url(fetcher.options(), name))?
+        .with_context(|| format!("no manifest found for toolchain {}", name))?;
+
+    if let Some(reason) =
+        bad_builds::reason(&requirements.bad_builds, &query.channel, &manifest)
+    {
+        bail!("{} is marked bad: {}", name, reason);
+    }
+    if requirements.is_viable(&manifest) {
+        println!("{} has everything required", name);
+        return Ok(());
+    }
+    let served = requirements.served_targets(&manifest);
+    let unserved = requirements
+        .targets()
+        .into_iter()
+        .filter(|target| !served.contains(target))
+        .collect::<Vec<_>>();
+    if !unserved.is_empty() {
+        println!("{} is incomplete on {}", name, unserved.join(", "));
+    }
+    bail!("{} doesn't have everything required", name);
+}
@@ -0,0 +1,169 @@
+//! Telling whether a toolchain rustup already installed can be completed with
+//! more components and targets, or whether it has to be replaced by a newer
+//! build.
+
+use crate::{
+    has_components,
+    make_toolchain_name,
+    manifests_in,
+    rustup,
+    toolchain_file::Pin,
+    toolchain_manifest_url,
+    After,
+    Manifest,
+    Session,
+    CURRENT_TARGET,
+};
+use anyhow::{bail, Context, Result};
+use std::process::Command;
+
+/// The components rustup installed for `toolchain`, like `rustc-<host>` and
+/// `rust-std-<target>`.
+pub fn installed_components(toolchain: &str) -> Result<Vec<String>> {
+    let output = Command::new("rustup")
+        .args(&["component", "list", "--installed", "--toolchain", toolchain])
+        .output()
+        .context("error running rustup")?;
+    if !output.status.success() {
+        bail!(
+            "error listing the components of {}: {}",
+            toolchain,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(str::to_owned)
+        .collect())
+}
+
+/// A component, which is needed for the host, or the standard library for a
+/// target.
+enum Need {
+    Component(String),
+    Target(String),
+}
+
+impl Need {
+    fn is_installable(&self, manifest: &Manifest, host: &str) -> bool {
+        match self {
+            Need::Component(component) => {
+                has_components(manifest, &[component.clone()], &[host])
+            },
+            Need::Target(target) => has_components(
+                manifest,
+                &["rust-std".to_owned()],
+                &[target.as_str()],
+            ),
+        }
+    }
+
+    fn is_installed(&self, installed: &[String], host: &str) -> bool {
+        let names = match self {
+            Need::Component(component) => {
+                vec![component.clone(), format!("{}-{}", component, host)]
+            },
+            Need::Target(target) => vec![format!("rust-std-{}", target)],
+        };
+        installed.iter().any(|name| names.contains(name))
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            Need::Component(component) => component.clone(),
+            Need::Target(target) => format!("target {}", target),
+        }
+    }
+
+    fn add_command(&self, toolchain: &str) -> String {
+        match self {
+            Need::Component(component) => format!(
+                "rustup component add --toolchain {} {}",
+                toolchain, component
+            ),
+            Need::Target(target) => format!(
+                "rustup target add --toolchain {} {}",
+                toolchain, target
+            ),
+        }
+    }
+}
+
+pub fn run(
+    session: &Session,
+    name: &str,
+    components: &[String],
+    targets: &[String],
+) -> Result<()> {
+    let host = rustup::settings()?
+        .default_host_triple
+        .unwrap_or_else(|| CURRENT_TARGET.to_owned());
+    let installed = installed_components(name)?;
+
+    let fetcher = &session.fetcher;
+    let manifest = fetcher
+        .manifest(&toolchain_manifest_url(fetcher.options(), name))?
+        .with_context(|| format!("no manifest found for toolchain {}", name))?;
+
+    let needs = components
+        .iter()
+        .cloned()
+        .map(Need::Component)
+        .chain(targets.iter().cloned().map(Need::Target))
+        .collect::<Vec<_>>();
+    let mut unavailable = false;
+    for need in &needs {
+        if need.is_installed(&installed, &host) {
+            println!("{} is installed", need.describe());
+        } else if need.is_installable(&manifest, &host) {
+            println!(
+                "{} isn't installed, add it with {}",
+                need.describe(),
+                need.add_command(name)
+            );
+        } else {
+            println!("{} isn't available for {}", need.describe(), name);
+            unavailable = true;
+        }
+    }
+    if !unavailable {
+        return Ok(());
+    }
+
+    let (channel, date) = match Pin::parse(name) {
+        Some(Pin {
+            channel,
+            date: Some(date),
+            ..
+        }) => (channel, date),
+        _ => {
+            println!("a newer pin is needed");
+            return Ok(());
+        },
+    };
+    // The nearest newer build is the smallest bump.
+    let mut nearest = None;
+    for manifest in manifests_in(fetcher, &channel, &After(date), &[])? {
+        let manifest = manifest?;
+        if manifest.date <= date {
+            break;
+        }
+        if needs
+            .iter()
+            .all(|need| need.is_installable(&manifest, &host))
+        {
+            nearest = Some(manifest);
+        }
+    }
+    match nearest {
+        Some(manifest) => println!(
+            "a newer pin is needed, the nearest with everything is {}",
+            make_toolchain_name(&manifest, &channel, true)
+        ),
+        None => println!(
+            "a newer pin is needed, but no newer {} build has everything",
+            channel
+        ),
+    }
+    Ok(())
+}
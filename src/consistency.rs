@@ -0,0 +1,90 @@
+//! Checking that every toolchain file in a repository pins the same
+//! toolchain, and optionally one a policy allows.
+
+use crate::{
+    find_files,
+    policy::{self, Policy},
+    toolchain_file::{self, Pin},
+    Session,
+};
+use anyhow::{bail, Result};
+use std::{collections::BTreeMap, path::Path};
+
+/// Why the policy doesn't allow the toolchain `name`, if it doesn't.
+fn policy_violation(policy: &Policy, name: &str) -> Option<String> {
+    let pin = Pin::parse(name);
+    let channel = pin.as_ref().map_or(name, |pin| pin.channel.as_str());
+    if policy.check_channel(channel).is_err() {
+        return Some(format!("channel {} is not allowed by policy", channel));
+    }
+    let pin = pin?;
+    if let Some(date) = pin.date {
+        if policy.deny_dates.contains(&date) {
+            return Some(format!("{} is denied by policy", date));
+        }
+    }
+    if let Some(version) = &pin.version {
+        if policy.deny_versions.contains(version) {
+            return Some(format!("{} is denied by policy", version));
+        }
+    }
+    None
+}
+
+pub fn run(
+    session: &Session,
+    root: &Path,
+    policy_location: Option<&str>,
+    policy_key: Option<&str>,
+) -> Result<()> {
+    let policy = match policy_location {
+        Some(location) => {
+            policy::load(session.fetcher.client(), location, policy_key)?
+        },
+        None => Policy::default(),
+    };
+
+    let mut paths = Vec::new();
+    find_files(
+        root,
+        &|name| toolchain_file::FILE_NAMES.contains(&name),
+        &mut paths,
+    )?;
+    paths.sort();
+    if paths.is_empty() {
+        bail!("no toolchain files found in {}", root.display());
+    }
+
+    let mut pins = BTreeMap::<_, usize>::new();
+    let mut violations = 0;
+    for path in &paths {
+        let name = toolchain_file::read_channel(path)?;
+        let label = name.as_deref().unwrap_or("no channel pinned");
+        match name
+            .as_deref()
+            .and_then(|name| policy_violation(&policy, name))
+        {
+            Some(reason) => {
+                violations += 1;
+                println!("{}: {}, {}", path.display(), label, reason);
+            },
+            None => println!("{}: {}", path.display(), label),
+        }
+        *pins.entry(label.to_owned()).or_default() += 1;
+    }
+
+    if pins.len() > 1 {
+        let drift = pins
+            .iter()
+            .map(|(label, count)| {
+                let files = if *count == 1 { "file" } else { "files" };
+                format!("{} ({} {})", label, count, files)
+            })
+            .collect::<Vec<_>>();
+        bail!("toolchain files disagree: {}", drift.join(", "));
+    }
+    if violations > 0 {
+        bail!("{} toolchain files violate the policy", violations);
+    }
+    Ok(())
+}
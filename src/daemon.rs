@@ -0,0 +1,243 @@
+//! A long-running process that answers toolchain queries over a local socket.
+//!
+//! The protocol is a single request line per connection containing the same
+//! arguments the CLI takes as a JSON array. The daemon replies with a JSON
+//! object holding the `output` the CLI would have printed or the `error` it
+//! failed with, and the `warnings` and `notes` resolving it ran into, then
+//! closes the connection. Successful results are cached so repeated queries
+//! are answered without touching the network.
+
+#[cfg(all(unix, feature = "serve"))]
+use crate::Query;
+use crate::Session;
+use anyhow::Result;
+#[cfg(all(unix, feature = "serve"))]
+use anyhow::{bail, Context};
+#[cfg(all(unix, feature = "serve"))]
+use clap::Parser;
+#[cfg(all(unix, feature = "serve"))]
+use serde::Deserialize;
+#[cfg(all(unix, feature = "serve"))]
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+#[cfg(all(unix, feature = "serve"))]
+use std::{
+    collections::HashMap,
+    io::{BufRead, BufReader, Read, Write},
+    os::unix::net::{UnixListener, UnixStream},
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+const SOCKET_NAME: &str = "rust-latest.sock";
+
+pub fn default_socket_path() -> PathBuf {
+    if let Some(path) = std::env::var_os("RUST_LATEST_SOCKET") {
+        return path.into();
+    }
+    std::env::var_os("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(std::env::temp_dir)
+        .join(SOCKET_NAME)
+}
+
+#[cfg(all(unix, feature = "serve"))]
+type Cache = Mutex<HashMap<Vec<String>, (Instant, String)>>;
+
+/// Answers queries with the fetcher of `session`, shared by all clients so
+/// each dated manifest is downloaded once.
+#[cfg(all(unix, feature = "serve"))]
+struct Daemon {
+    session: Session,
+    cache: Cache,
+    refresh: Duration,
+    /// Held while resolving, so the diagnostics taken from the shared
+    /// fetcher afterwards are only the query's own.
+    resolving: Mutex<()>,
+}
+
+#[cfg(all(unix, feature = "serve"))]
+pub fn serve(
+    session: &Session,
+    socket: &Path,
+    refresh: Duration,
+) -> Result<()> {
+    if socket.exists() {
+        if UnixStream::connect(socket).is_ok() {
+            bail!("a daemon is already listening on {}", socket.display());
+        }
+        std::fs::remove_file(socket).with_context(|| {
+            format!("error removing stale socket {}", socket.display())
+        })?;
+    }
+    let listener = UnixListener::bind(socket)
+        .with_context(|| format!("error binding to {}", socket.display()))?;
+    eprintln!("listening on {}", socket.display());
+
+    let daemon = Arc::new(Daemon {
+        session: session.clone(),
+        cache: Cache::default(),
+        refresh,
+        resolving: Mutex::new(()),
+    });
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(error) => {
+                eprintln!("error accepting connection: {}", error);
+                continue;
+            },
+        };
+        let daemon = Arc::clone(&daemon);
+        thread::spawn(move || {
+            if let Err(error) = daemon.handle(stream) {
+                eprintln!("error handling query: {:#}", error);
+            }
+        });
+    }
+
+    Ok(())
+}
+
+#[cfg(all(unix, feature = "serve"))]
+impl Daemon {
+    fn handle(&self, stream: UnixStream) -> Result<()> {
+        let mut line = String::new();
+        BufReader::new(&stream)
+            .read_line(&mut line)
+            .context("error reading query")?;
+        let response = match serde_json::from_str::<Vec<String>>(&line) {
+            Ok(args) => self.answer(args),
+            Err(error) => json!({ "error": format!("bad query: {}", error) }),
+        };
+        let mut stream = &stream;
+        writeln!(stream, "{}", response).context("error writing response")
+    }
+
+    /// The reply to the query of `args`, cached for `refresh` if it
+    /// succeeded.
+    fn answer(&self, args: Vec<String>) -> Value {
+        let cached = self
+            .cache
+            .lock()
+            .unwrap()
+            .get(&args)
+            .filter(|(resolved_at, _)| resolved_at.elapsed() < self.refresh)
+            .map(|(_, output)| output.clone());
+        if let Some(output) = cached {
+            return json!({ "output": output });
+        }
+
+        let query = match Query::try_parse_from(
+            std::iter::once("rust-latest".to_owned()).chain(args.clone()),
+        ) {
+            Ok(query) if query.channel == "auto" => {
+                return json!({
+                    "error": "--channel auto is not supported by the daemon",
+                });
+            },
+            Ok(query) => query,
+            Err(error) => return json!({ "error": error.to_string() }),
+        };
+        let fetcher = &self.session.fetcher;
+        let (result, diagnostics) = {
+            let _resolving = self.resolving.lock().unwrap();
+            // Each query sees the channels as they are now.
+            fetcher.forget_latest();
+            let result = crate::resolve(&self.session, &query);
+            (result, fetcher.take_diagnostics())
+        };
+        let (warnings, notes): (Vec<_>, Vec<_>) = diagnostics
+            .into_iter()
+            .partition(|diagnostic| diagnostic.is_warning());
+        let to_strings = |diagnostics: Vec<_>| {
+            diagnostics
+                .into_iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+        };
+        let mut response = json!({
+            "warnings": to_strings(warnings),
+            "notes": to_strings(notes),
+        });
+        match result {
+            Ok(output) => {
+                self.cache
+                    .lock()
+                    .unwrap()
+                    .insert(args, (Instant::now(), output.clone()));
+                response["output"] = output.into();
+            },
+            Err(error) => response["error"] = format!("{:#}", error).into(),
+        }
+        response
+    }
+}
+
+/// The reply of the daemon to a query.
+#[cfg(all(unix, feature = "serve"))]
+#[derive(Deserialize)]
+struct Response {
+    output: Option<String>,
+    error: Option<String>,
+    #[serde(default)]
+    warnings: Vec<String>,
+    #[serde(default)]
+    notes: Vec<String>,
+}
+
+/// Asks the daemon listening on `socket` to resolve `args`, returning `None`
+/// if no daemon is running. What the daemon ran into is reported through
+/// `session` as if the query was resolved here.
+#[cfg(all(unix, feature = "serve"))]
+pub fn query(
+    session: &Session,
+    socket: &Path,
+    args: &[String],
+) -> Result<Option<String>> {
+    let mut stream = match UnixStream::connect(socket) {
+        Ok(stream) => stream,
+        Err(_) => return Ok(None),
+    };
+    writeln!(stream, "{}", serde_json::to_string(args)?)
+        .context("error sending query")?;
+    let mut response = String::new();
+    stream
+        .read_to_string(&mut response)
+        .context("error reading response")?;
+    let response = serde_json::from_str::<Response>(&response)
+        .context("malformed response from daemon")?;
+    for warning in &response.warnings {
+        session.warn(warning);
+    }
+    for note in &response.notes {
+        eprintln!("note: {}", note);
+    }
+    match (response.output, response.error) {
+        (Some(output), _) => Ok(Some(output)),
+        (None, Some(message)) => bail!("daemon: {}", message),
+        (None, None) => bail!("malformed response from daemon"),
+    }
+}
+
+#[cfg(not(all(unix, feature = "serve")))]
+pub fn serve(
+    _session: &Session,
+    _socket: &Path,
+    _refresh: std::time::Duration,
+) -> Result<()> {
+    anyhow::bail!(
+        "the daemon is only supported on Unix platforms, in builds with the \
+         serve feature"
+    )
+}
+
+#[cfg(not(all(unix, feature = "serve")))]
+pub fn query(
+    _session: &Session,
+    _socket: &Path,
+    _args: &[String],
+) -> Result<Option<String>> {
+    Ok(None)
+}
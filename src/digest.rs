@@ -0,0 +1,188 @@
+//! Summarizing the last days of releases: stable and beta releases, the
+//! nightlies complete with the default profile, and the components that went
+//! missing or came back.
+
+use crate::{
+    filter_manifest,
+    has_components,
+    make_toolchain_name,
+    manifests,
+    DigestOutputOpt,
+    Manifest,
+    Session,
+    TargetsOpt,
+    CURRENT_TARGET,
+};
+use anyhow::{Context, Result};
+use chrono::{Duration, NaiveDate};
+use std::collections::BTreeSet;
+
+/// The full version of the build's `rust` package, like `1.80.0-beta.3`.
+fn full_version(manifest: &Manifest) -> Option<&str> {
+    manifest
+        .packages
+        .get("rust")?
+        .version
+        .split_whitespace()
+        .next()
+}
+
+/// The release of `channel` made since `since`, if its version changed.
+fn release(
+    session: &Session,
+    channel: &str,
+    since: NaiveDate,
+) -> Result<Option<String>> {
+    let fetcher = &session.fetcher;
+    let latest = fetcher
+        .manifest(&fetcher.options().manifest_url(channel))?
+        .with_context(|| {
+            format!("no manifest found for release channel {}", channel)
+        })?;
+    if latest.date < since {
+        return Ok(None);
+    }
+    let before = fetcher.manifest(
+        &fetcher
+            .options()
+            .dated_manifest_url(since - Duration::days(1), channel),
+    )?;
+    let version = full_version(&latest).unwrap_or_default();
+    if before.as_ref().and_then(full_version) == Some(version) {
+        return Ok(None);
+    }
+    Ok(Some(format!(
+        "{} {} ({})",
+        channel,
+        version,
+        session.human_date(latest.date)
+    )))
+}
+
+fn is_complete(manifest: &Manifest, targets: &[&str]) -> bool {
+    let profile = match manifest.profiles.get("default") {
+        Some(profile) => profile.iter().map(String::as_str).collect::<Vec<_>>(),
+        None => return false,
+    };
+    filter_manifest(manifest, &profile, &[], targets)
+}
+
+fn render(
+    output: DigestOutputOpt,
+    title: &str,
+    sections: &[(&str, Vec<String>)],
+) -> String {
+    let mut lines = Vec::new();
+    match output {
+        DigestOutputOpt::Markdown => lines.push(format!("# {}", title)),
+        DigestOutputOpt::Plain => lines.push(title.to_owned()),
+    }
+    for (heading, items) in sections {
+        lines.push(String::new());
+        match output {
+            DigestOutputOpt::Markdown => lines.push(format!("## {}", heading)),
+            DigestOutputOpt::Plain => lines.push(format!("{}:", heading)),
+        }
+        let items = if items.is_empty() {
+            vec!["none".to_owned()]
+        } else {
+            items.clone()
+        };
+        for item in items {
+            match output {
+                DigestOutputOpt::Markdown => lines.push(format!("- {}", item)),
+                DigestOutputOpt::Plain => lines.push(format!("  {}", item)),
+            }
+        }
+    }
+    lines.join("\n")
+}
+
+pub fn run(
+    session: &Session,
+    days: usize,
+    targets: TargetsOpt,
+    output: DigestOutputOpt,
+) -> Result<()> {
+    let targets = targets
+        .triples(session.tier_list.as_deref())?
+        .unwrap_or_else(|| vec![CURRENT_TARGET.to_owned()]);
+    let targets = &targets.iter().map(String::as_str).collect::<Vec<_>>();
+
+    // One more day than reported, to tell what changed on the first one.
+    let mut nightlies = manifests(&session.fetcher, "nightly", days + 1, &[])?
+        .collect::<Result<Vec<_>>>()?;
+    nightlies.reverse();
+    let latest_date = match nightlies.last() {
+        Some(latest) => latest.date,
+        None => return Ok(()),
+    };
+    let since = latest_date - Duration::days(days as i64 - 1);
+    let toolchain_name =
+        |manifest: &Manifest| make_toolchain_name(manifest, "nightly", true);
+
+    let mut releases = Vec::new();
+    for channel in &["stable", "beta"] {
+        releases.extend(release(session, channel, since)?);
+    }
+
+    let complete = nightlies
+        .iter()
+        .rev()
+        .filter(|manifest| manifest.date >= since)
+        .filter(|manifest| is_complete(manifest, targets))
+        .map(toolchain_name)
+        .collect::<Vec<_>>();
+
+    let components = nightlies
+        .iter()
+        .flat_map(|manifest| manifest.packages.keys())
+        .cloned()
+        .collect::<BTreeSet<_>>();
+    let mut changes = Vec::new();
+    for component in &components {
+        let component = [component.clone()];
+        for pair in nightlies.windows(2) {
+            let (before, after) = (&pair[0], &pair[1]);
+            if after.date < since
+                || !before.packages.contains_key(&component[0])
+            {
+                continue;
+            }
+            let was_available = has_components(before, &component, targets);
+            let is_available = has_components(after, &component, targets);
+            if was_available && !is_available {
+                changes.push(format!(
+                    "{} went missing in {}",
+                    component[0],
+                    toolchain_name(after)
+                ));
+            } else if !was_available && is_available {
+                changes.push(format!(
+                    "{} came back in {}",
+                    component[0],
+                    toolchain_name(after)
+                ));
+            }
+        }
+    }
+
+    let title = format!(
+        "Rust toolchains from {} to {}",
+        session.human_date(since),
+        session.human_date(latest_date)
+    );
+    println!(
+        "{}",
+        render(
+            output,
+            &title,
+            &[
+                ("Releases", releases),
+                ("Complete nightlies", complete),
+                ("Component changes", changes),
+            ]
+        )
+    );
+    Ok(())
+}
@@ -0,0 +1,79 @@
+//! Looking up the digests of the official `rust` container images, so image
+//! builds can pin the toolchain by digest rather than by a moving tag.
+
+use crate::http::Client;
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+const TOKEN_URL: &str = "https://auth.docker.io/token\
+                         ?service=registry.docker.io\
+                         &scope=repository:library/rust:pull";
+const MANIFESTS_URL: &str =
+    "https://registry-1.docker.io/v2/library/rust/manifests";
+
+/// Media types of multi-platform image indexes, in both the OCI and the older
+/// Docker format.
+const INDEX_TYPES: &str = "application/vnd.oci.image.index.v1+json, \
+                           application/vnd.docker.distribution.manifest.list.\
+                           v2+json";
+
+#[derive(Deserialize)]
+struct Token {
+    token: String,
+}
+
+#[derive(Deserialize)]
+struct Index {
+    manifests: Vec<Entry>,
+}
+
+#[derive(Deserialize)]
+struct Entry {
+    digest: String,
+    platform: Platform,
+}
+
+#[derive(Deserialize)]
+struct Platform {
+    os: String,
+    architecture: String,
+    variant: Option<String>,
+}
+
+/// The digest of each platform's image of `rust:<tag>`, like `linux/arm64/v8`,
+/// in the order the registry lists them.
+pub fn digests(client: &Client, tag: &str) -> Result<Vec<(String, String)>> {
+    let token = client
+        .get(TOKEN_URL)?
+        .context("no registry token endpoint")?;
+    let token = serde_json::from_slice::<Token>(&token)
+        .context("error parsing registry token")?
+        .token;
+
+    let url = format!("{}/{}", MANIFESTS_URL, tag);
+    let authorization = format!("Bearer {}", token);
+    let index = client
+        .get_with_headers(
+            &url,
+            &[("Authorization", &authorization), ("Accept", INDEX_TYPES)],
+        )?
+        .with_context(|| format!("no rust:{} image found", tag))?;
+    let index = serde_json::from_slice::<Index>(&index)
+        .with_context(|| format!("error parsing the index of rust:{}", tag))?;
+
+    Ok(index
+        .manifests
+        .into_iter()
+        // Attestations are listed as images of an unknown platform.
+        .filter(|entry| entry.platform.os != "unknown")
+        .map(|entry| {
+            let mut platform =
+                format!("{}/{}", entry.platform.os, entry.platform.architecture);
+            if let Some(variant) = &entry.platform.variant {
+                platform.push('/');
+                platform.push_str(variant);
+            }
+            (platform, entry.digest)
+        })
+        .collect())
+}
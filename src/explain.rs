@@ -0,0 +1,66 @@
+//! Describing what exactly a toolchain name refers to.
+
+use crate::{toolchain_manifest_url, Session, TargetsOpt};
+use anyhow::{bail, Result};
+
+pub fn run(session: &Session, name: &str) -> Result<()> {
+    let url = toolchain_manifest_url(session.fetcher.options(), name);
+    let manifest = match session.fetcher.manifest(&url)? {
+        Some(manifest) => manifest,
+        None => bail!("no manifest found for toolchain {}", name),
+    };
+
+    println!("toolchain: {}", name);
+    println!("date: {}", session.human_date(manifest.date));
+    if let Some(rustc) = manifest.packages.get("rustc") {
+        println!("rustc: {}", rustc.version);
+        if let Some(commit) = &rustc.git_commit_hash {
+            println!("commit: {}", commit);
+        }
+    }
+
+    let mut packages = manifest.packages.iter().collect::<Vec<_>>();
+    packages.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    println!("components:");
+    for (package, package_targets) in &packages {
+        if !package_targets.version.is_empty() {
+            println!("  {} {}", package, package_targets.version);
+        }
+    }
+
+    // The same Tier-1 targets queries require, including a --tier-list file.
+    let tier1 = TargetsOpt::All
+        .triples(session.tier_list.as_deref())?
+        .unwrap_or_default();
+    let gaps = packages
+        .iter()
+        .filter_map(|(package, package_targets)| {
+            let missing = tier1
+                .iter()
+                .map(String::as_str)
+                .filter(|&target| {
+                    package_targets
+                        .targets
+                        .get(target)
+                        .map_or(false, |package_info| !package_info.available)
+                })
+                .collect::<Vec<_>>();
+            if missing.is_empty() {
+                None
+            } else {
+                Some((package, missing))
+            }
+        })
+        .collect::<Vec<_>>();
+    if gaps.is_empty() {
+        println!("complete on all Tier-1 targets");
+    } else {
+        println!("missing on Tier-1 targets:");
+        for (package, missing) in gaps {
+            println!("  {}: {}", package, missing.join(", "));
+        }
+    }
+
+    Ok(())
+}
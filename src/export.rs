@@ -0,0 +1,109 @@
+//! Writing the availability of every package on every target over a range of
+//! days as one row each, for analysis outside the tool.
+
+use crate::{manifests, ExportFieldOpt, ExportOutputOpt, Manifest, Session};
+use anyhow::Result;
+use std::io::{self, Write};
+
+struct Row<'a> {
+    manifest: &'a Manifest,
+    package: &'a str,
+    target: &'a str,
+    available: bool,
+    version: &'a str,
+}
+
+impl Row<'_> {
+    fn value(&self, field: ExportFieldOpt) -> serde_json::Value {
+        match field {
+            ExportFieldOpt::Date => self.manifest.date.to_string().into(),
+            ExportFieldOpt::Component => self.package.into(),
+            ExportFieldOpt::Target => self.target.into(),
+            ExportFieldOpt::Available => self.available.into(),
+            ExportFieldOpt::Version if self.version.is_empty() => {
+                serde_json::Value::Null
+            },
+            ExportFieldOpt::Version => self.version.into(),
+        }
+    }
+}
+
+/// Quotes a CSV field if it has a separator, quote or line break in it.
+fn csv_field(value: &serde_json::Value) -> String {
+    let value = match value {
+        serde_json::Value::String(value) => value.clone(),
+        serde_json::Value::Null => String::new(),
+        value => value.to_string(),
+    };
+    if value.contains(&[',', '"', '\n', '\r'][..]) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value
+    }
+}
+
+fn write_row(
+    out: &mut impl Write,
+    output: ExportOutputOpt,
+    fields: &[ExportFieldOpt],
+    row: &Row,
+) -> Result<()> {
+    match output {
+        ExportOutputOpt::Jsonl => {
+            let object = fields
+                .iter()
+                .map(|&field| (field.name().to_owned(), row.value(field)))
+                .collect::<serde_json::Map<_, _>>();
+            writeln!(out, "{}", serde_json::Value::Object(object))?;
+        },
+        ExportOutputOpt::Csv => {
+            let values = fields
+                .iter()
+                .map(|&field| csv_field(&row.value(field)))
+                .collect::<Vec<_>>();
+            writeln!(out, "{}", values.join(","))?;
+        },
+    }
+    Ok(())
+}
+
+/// Writes rows as the manifests come in, so a long export shows progress and
+/// keeps only one batch of manifests in memory.
+pub fn run(
+    session: &Session,
+    channel: &str,
+    days: usize,
+    output: ExportOutputOpt,
+    fields: &[ExportFieldOpt],
+) -> Result<()> {
+    let stdout = io::stdout();
+    let mut out = io::BufWriter::new(stdout.lock());
+    if output == ExportOutputOpt::Csv {
+        let names = fields.iter().map(|field| field.name()).collect::<Vec<_>>();
+        writeln!(out, "{}", names.join(","))?;
+    }
+
+    let fetcher = session.fetcher.clone().forgetful();
+    for manifest in manifests(&fetcher, channel, days, &[])? {
+        let manifest = manifest?;
+        let mut packages = manifest.packages.iter().collect::<Vec<_>>();
+        packages.sort_by(|(a, _), (b, _)| a.cmp(b));
+        for (package, package_targets) in packages {
+            let mut targets =
+                package_targets.targets.iter().collect::<Vec<_>>();
+            targets.sort_by(|(a, _), (b, _)| a.cmp(b));
+            for (target, package_info) in targets {
+                let row = Row {
+                    manifest: &manifest,
+                    package,
+                    target,
+                    available: package_info.available,
+                    version: &package_targets.version,
+                };
+                write_row(&mut out, output, fields, &row)?;
+            }
+        }
+        out.flush()?;
+    }
+    Ok(())
+}
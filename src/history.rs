@@ -0,0 +1,39 @@
+//! Listing, for each day in the search window, whether the channel's build
+//! had everything a query requires, to see how often it breaks.
+
+use crate::{manifests, Query, Requirements, Session};
+use anyhow::Result;
+
+pub fn run(session: &Session, query: &Query) -> Result<()> {
+    let requirements = Requirements::new(session, query)?;
+    let mut days = 0;
+    let mut viable_days = 0;
+    for manifest in manifests(
+        &session.fetcher,
+        &query.channel,
+        requirements.max_age,
+        &requirements.skip_dates,
+    )? {
+        let manifest = manifest?;
+        days += 1;
+        let date = session.human_date(manifest.date);
+        if requirements.is_viable(&manifest) {
+            viable_days += 1;
+            println!("{} viable", date);
+            continue;
+        }
+        let missing = requirements.missing_components(&manifest);
+        if missing.is_empty() {
+            // Everything is there, but the policy or a component version
+            // rules the build out.
+            println!("{} not viable", date);
+        } else {
+            println!("{} missing {}", date, missing.join(", "));
+        }
+    }
+    println!(
+        "{} was viable on {} of {} days with a release",
+        query.channel, viable_days, days
+    );
+    Ok(())
+}
@@ -0,0 +1,165 @@
+//! Installing the resolved toolchain with rustup, along with the components
+//! and targets it was selected for, and pinning directories to it.
+
+use crate::{
+    check_installed::installed_components,
+    has_components,
+    rustup,
+    toolchain_manifest_url,
+    Query,
+    Requirements,
+    Session,
+    CURRENT_TARGET,
+};
+use anyhow::{bail, Context, Result};
+use std::{path::Path, process::Command};
+
+/// Runs a rustup command, or only prints it if `dry_run` is set.
+pub fn rustup(args: &[String], dry_run: bool) -> Result<()> {
+    if dry_run {
+        println!("rustup {}", args.join(" "));
+        return Ok(());
+    }
+    eprintln!("running rustup {}", args.join(" "));
+    let status = Command::new("rustup")
+        .args(args)
+        .status()
+        .context("error running rustup")?;
+    if !status.success() {
+        bail!("rustup {} failed with {}", args.join(" "), status);
+    }
+    Ok(())
+}
+
+/// Installs `toolchain` with the profile and components it was resolved
+/// with. Targets are only installed when given with `--target`, since a
+/// search over a set of targets is usually about portability rather than
+/// cross-compiling.
+pub fn run(
+    requirements: &Requirements,
+    query: &Query,
+    toolchain: &str,
+    dry_run: bool,
+) -> Result<()> {
+    let mut args = vec![
+        "toolchain".to_owned(),
+        "install".to_owned(),
+        toolchain.to_owned(),
+        "--profile".to_owned(),
+        requirements.profile.name().to_owned(),
+    ];
+    let components = requirements.components();
+    if !components.is_empty() {
+        args.push("--component".to_owned());
+        args.push(components.join(","));
+    }
+    if !query.target_triples.is_empty() {
+        args.push("--target".to_owned());
+        args.push(query.target_triples.join(","));
+    }
+    rustup(&args, dry_run)
+}
+
+/// The commit hash `rustc -vV` of `toolchain` reports.
+fn rustc_commit(toolchain: &str) -> Result<Option<String>> {
+    let output = Command::new("rustup")
+        .args(&["run", toolchain, "rustc", "-vV"])
+        .output()
+        .context("error running rustup")?;
+    if !output.status.success() {
+        bail!(
+            "error running rustc of {}: {}",
+            toolchain,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find_map(|line| line.strip_prefix("commit-hash: "))
+        .map(str::to_owned))
+}
+
+/// Checks that rustup installed the components and targets `toolchain` was
+/// installed with, and the rustc its manifest lists, so a partially failed
+/// install fails here instead of in the build.
+pub fn verify(
+    session: &Session,
+    requirements: &Requirements,
+    query: &Query,
+    toolchain: &str,
+) -> Result<()> {
+    let fetcher = &session.fetcher;
+    let manifest = fetcher
+        .manifest(&toolchain_manifest_url(fetcher.options(), toolchain))?
+        .with_context(|| {
+            format!("no manifest found for toolchain {}", toolchain)
+        })?;
+    let host = rustup::settings()?
+        .default_host_triple
+        .unwrap_or_else(|| CURRENT_TARGET.to_owned());
+    // rustup lists components under their stable names, like clippy for
+    // clippy-preview.
+    let installed = installed_components(toolchain)?
+        .iter()
+        .map(|name| name.replace("-preview", ""))
+        .collect::<Vec<_>>();
+
+    let mut missing = Vec::new();
+    for component in requirements.components() {
+        // What the manifest doesn't ship for the host was let through by
+        // --unknown-component and can't be installed.
+        let components = [component.to_owned()];
+        if !has_components(&manifest, &components, &[host.as_str()]) {
+            continue;
+        }
+        let name = component.replace("-preview", "");
+        let host_name = format!("{}-{}", name, host);
+        if !installed.contains(&name) && !installed.contains(&host_name) {
+            missing.push(component.to_owned());
+        }
+    }
+    for target in &query.target_triples {
+        if !installed.contains(&format!("rust-std-{}", target)) {
+            missing.push(format!("target {}", target));
+        }
+    }
+    if !missing.is_empty() {
+        bail!("{} was installed without {}", toolchain, missing.join(", "));
+    }
+
+    let expected = manifest
+        .packages
+        .get("rustc")
+        .and_then(|rustc| rustc.git_commit_hash.as_deref());
+    let actual = rustc_commit(toolchain)?;
+    if let (Some(expected), Some(actual)) = (expected, &actual) {
+        if expected != actual {
+            bail!(
+                "rustc of {} is built from {}, but its manifest lists {}",
+                toolchain,
+                actual,
+                expected
+            );
+        }
+    }
+    println!("verified the install of {}", toolchain);
+    Ok(())
+}
+
+/// Makes rustup use `toolchain` in `directory`, or the current directory.
+pub fn set_override(
+    toolchain: &str,
+    directory: Option<&Path>,
+    dry_run: bool,
+) -> Result<()> {
+    let mut args = vec![
+        "override".to_owned(),
+        "set".to_owned(),
+        toolchain.to_owned(),
+    ];
+    if let Some(directory) = directory {
+        args.push("--path".to_owned());
+        args.push(directory.display().to_string());
+    }
+    rustup(&args, dry_run)
+}
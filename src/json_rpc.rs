@@ -0,0 +1,204 @@
+//! Serving queries as JSON-RPC 2.0 over stdio, one message per line, so an
+//! editor can keep a single process running as its backend.
+//!
+//! - `resolve` takes a query's arguments and returns what the query prints.
+//! - `check` takes an optional `{"dir": ...}` and tells whether the toolchain
+//!   the project there pins is behind the latest viable build of its channel.
+//! - `history` takes a query's arguments and lists every viable build in the
+//!   search window, newest first.
+//!
+//! There are no progress notifications. Each request gets its one response
+//! once the method is done.
+
+use crate::{
+    find_viable_manifest,
+    get_rust_version,
+    make_toolchain_name,
+    manifests,
+    resolve,
+    toolchain_file::{self, Pin},
+    Query,
+    Requirements,
+    Session,
+};
+use anyhow::{Context, Result};
+use clap::Parser;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::{
+    io::{BufRead, Write},
+    path::PathBuf,
+};
+
+const PARSE_ERROR: i64 = -32700;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+/// Any failure of the method itself, like a query without a viable build.
+const SERVER_ERROR: i64 = -32000;
+
+#[derive(Deserialize)]
+struct Request {
+    /// Missing for notifications, which get no response.
+    id: Option<Value>,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct CheckParams {
+    dir: Option<PathBuf>,
+}
+
+fn parse_query(params: Value) -> Result<Query, (i64, String)> {
+    let args = match params {
+        Value::Null => Vec::new(),
+        params => {
+            serde_json::from_value::<Vec<String>>(params).map_err(|error| {
+                (INVALID_PARAMS, format!("expected arguments: {}", error))
+            })?
+        },
+    };
+    let mut query = Query::try_parse_from(
+        std::iter::once("rust-latest".to_owned()).chain(args),
+    )
+    .map_err(|error| (INVALID_PARAMS, error.to_string()))?;
+    query
+        .detect_project()
+        .map_err(|error| (SERVER_ERROR, format!("{:#}", error)))?;
+    Ok(query)
+}
+
+fn history(session: &Session, query: &Query) -> Result<Value> {
+    let requirements = Requirements::new(session, query)?;
+    let mut builds = Vec::new();
+    for manifest in manifests(
+        &session.fetcher,
+        &query.channel,
+        requirements.max_age,
+        &requirements.skip_dates,
+    )? {
+        let manifest = manifest?;
+        if !requirements.is_viable(&manifest) {
+            continue;
+        }
+        builds.push(json!({
+            "toolchain": make_toolchain_name(
+                &manifest,
+                &query.channel,
+                query.force_date,
+            ),
+            "date": manifest.date.to_string(),
+            "version": get_rust_version(&manifest),
+        }));
+    }
+    Ok(Value::Array(builds))
+}
+
+fn check(session: &Session, params: CheckParams) -> Result<Value> {
+    let dir = match params.dir {
+        Some(dir) => dir,
+        None => std::env::current_dir()
+            .context("error getting current directory")?,
+    };
+    let name = match toolchain_file::find(&dir) {
+        Some(path) => toolchain_file::read_channel(&path)?,
+        None => None,
+    };
+    let (name, pin) = match name {
+        Some(name) => match Pin::parse(&name) {
+            Some(pin) => (name, pin),
+            None => return Ok(json!({ "pinned": name, "latest": null })),
+        },
+        None => return Ok(json!({ "pinned": null, "latest": null })),
+    };
+
+    let mut query = Query::parse_from(&["rust-latest", "-t", "current"]);
+    query.channel = pin.channel.clone();
+    let latest = find_viable_manifest(session, &query)?;
+    let days_behind = pin
+        .date
+        .map(|date| latest.date.signed_duration_since(date).num_days());
+    let up_to_date = !pin.is_behind(&latest);
+    let latest_name =
+        make_toolchain_name(&latest, &pin.channel, pin.date.is_some());
+    Ok(json!({
+        "pinned": name,
+        "latest": latest_name,
+        "days_behind": days_behind,
+        "up_to_date": up_to_date,
+    }))
+}
+
+fn call(session: &Session, request: Request) -> Result<Value, (i64, String)> {
+    let failed = |error: anyhow::Error| (SERVER_ERROR, format!("{:#}", error));
+    match request.method.as_str() {
+        "resolve" => {
+            let query = parse_query(request.params)?;
+            resolve(session, &query).map(Value::String).map_err(failed)
+        },
+        "check" => {
+            let params = match request.params {
+                Value::Null => CheckParams::default(),
+                params => serde_json::from_value(params)
+                    .map_err(|error| (INVALID_PARAMS, error.to_string()))?,
+            };
+            check(session, params).map_err(failed)
+        },
+        "history" => {
+            let query = parse_query(request.params)?;
+            history(session, &query).map_err(failed)
+        },
+        method => Err((METHOD_NOT_FOUND, format!("unknown method {}", method))),
+    }
+}
+
+fn error_response(id: Value, code: i64, message: String) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": code, "message": message },
+    })
+}
+
+/// Serves the requests on stdin with the fetcher of `session`, kept across
+/// them so dated manifests are downloaded once per process.
+pub fn run(session: &Session) -> Result<()> {
+    let stdin = std::io::stdin();
+    let stdout = std::io::stdout();
+    let mut stdout = stdout.lock();
+    for line in stdin.lock().lines() {
+        let line = line.context("error reading request")?;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let response = match serde_json::from_str::<Request>(line) {
+            Ok(request) => {
+                // Each request sees the channels as they are now.
+                session.fetcher.forget_latest();
+                let id = request.id.clone();
+                let result = call(session, request);
+                session.report_diagnostics();
+                let id = match id {
+                    Some(id) => id,
+                    None => continue,
+                };
+                match result {
+                    Ok(result) => {
+                        json!({ "jsonrpc": "2.0", "id": id, "result": result })
+                    },
+                    Err((code, message)) => error_response(id, code, message),
+                }
+            },
+            Err(error) => {
+                error_response(Value::Null, PARSE_ERROR, error.to_string())
+            },
+        };
+        writeln!(stdout, "{}", response)
+            .and_then(|()| stdout.flush())
+            .context("error writing response")?;
+    }
+    Ok(())
+}
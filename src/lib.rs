@@ -0,0 +1,1192 @@
+//! Finding the latest Rust toolchain build that ships the components and
+//! targets a project needs.
+//!
+//! [`ToolchainFinder`] covers the common lookup:
+//!
+//! ```no_run
+//! # fn main() -> anyhow::Result<()> {
+//! let manifest = rust_latest::ToolchainFinder::new("nightly")
+//!     .profile("minimal")
+//!     .components(&["clippy", "rustfmt"])
+//!     .find()?;
+//! if let Some(manifest) = manifest {
+//!     println!(
+//!         "{}",
+//!         rust_latest::make_toolchain_name(&manifest, "nightly", false)
+//!     );
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`ToolchainFinder::viable_manifests`] lists every build it would accept,
+//! for choosing among them some other way, and
+//! [`ToolchainFinder::window`] changes which days it looks at. The functions
+//! it's built on are public too, for lookups it doesn't cover.
+//!
+//! Apart from finding rustup's own files, nothing is read from the
+//! environment or printed. [`Options`] say where manifests come from and how
+//! they're cached and downloaded, and a [`Fetcher`] keeps what it ran into
+//! along the way, like a mirror failing, as [`Diagnostic`]s for the caller.
+
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::{Datelike, Duration, NaiveDate};
+use http::{Client, Conditional, Validators};
+use regex::Regex;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::{
+    collections::HashMap,
+    fmt,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    thread,
+};
+
+pub mod cache;
+pub mod http;
+pub mod rustup;
+
+/// Something a [`Fetcher`] ran into that didn't stop it, kept for the caller
+/// to show or log with [`Fetcher::take_diagnostics`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Diagnostic {
+    /// Downloading from one dist server failed, so the next one is tried.
+    FallingBack { error: String, fallback: String },
+    /// A download couldn't be cached, so it'll be downloaded again next time.
+    NotCached { url: String, error: String },
+    /// A channel has more than one manifest of a date, from a respun release,
+    /// and only `kept` is looked at. Both are described by version and hash.
+    Respin {
+        channel: String,
+        date: NaiveDate,
+        kept: String,
+        dropped: String,
+    },
+}
+
+impl Diagnostic {
+    /// Whether the result may be worse for it, as opposed to just being
+    /// worth knowing.
+    pub fn is_warning(&self) -> bool {
+        !matches!(self, Diagnostic::Respin { .. })
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Diagnostic::FallingBack { error, fallback } => {
+                write!(f, "{}, trying {}", error, fallback)
+            },
+            Diagnostic::NotCached { url, error } => {
+                write!(f, "not caching {}: {}", url, error)
+            },
+            Diagnostic::Respin {
+                channel,
+                date,
+                kept,
+                dropped,
+            } => write!(
+                f,
+                "{} has more than one manifest dated {}: {} and {}, using {}",
+                channel, date, kept, dropped, kept
+            ),
+        }
+    }
+}
+
+/// The target this crate was built for.
+pub const CURRENT_TARGET: &str = env!("TARGET");
+
+/// All Rust Tier 1 targets as specified by
+/// [`rust-components-history`](https://github.com/rust-lang/rustup-components-history/blob/dc6890bde289ac72d9d16959e4432f72f30c051b/web/src/opts.rs#L115-L122).
+pub static TIER_1_TARGETS: &[&str] = &[
+    "i686-pc-windows-gnu",
+    "i686-pc-windows-msvc",
+    "i686-unknown-linux-gnu",
+    "x86_64-apple-darwin",
+    "x86_64-pc-windows-gnu",
+    "x86_64-pc-windows-msvc",
+    "x86_64-unknown-linux-gnu",
+    "aarch64-unknown-linux-gnu",
+];
+
+/// Tier 2 targets that rustup ships host tools like `cargo` and `rustc` for.
+pub static TIER_2_HOST_TOOLS_TARGETS: &[&str] = &[
+    "aarch64-apple-darwin",
+    "aarch64-pc-windows-msvc",
+    "aarch64-unknown-linux-musl",
+    "arm-unknown-linux-gnueabi",
+    "arm-unknown-linux-gnueabihf",
+    "armv7-unknown-linux-gnueabihf",
+    "powerpc-unknown-linux-gnu",
+    "powerpc64-unknown-linux-gnu",
+    "powerpc64le-unknown-linux-gnu",
+    "riscv64gc-unknown-linux-gnu",
+    "s390x-unknown-linux-gnu",
+    "x86_64-unknown-freebsd",
+    "x86_64-unknown-illumos",
+    "x86_64-unknown-linux-musl",
+    "x86_64-unknown-netbsd",
+];
+
+/// macOS and iOS targets, including the iOS simulators.
+pub static APPLE_TARGETS: &[&str] = &[
+    "aarch64-apple-darwin",
+    "aarch64-apple-ios",
+    "aarch64-apple-ios-sim",
+    "x86_64-apple-darwin",
+    "x86_64-apple-ios",
+];
+
+/// Windows targets with either the MSVC or the MinGW toolchain.
+pub static WINDOWS_TARGETS: &[&str] = &[
+    "aarch64-pc-windows-msvc",
+    "i686-pc-windows-gnu",
+    "i686-pc-windows-msvc",
+    "x86_64-pc-windows-gnu",
+    "x86_64-pc-windows-msvc",
+];
+
+/// The common Linux targets, with glibc or musl.
+pub static LINUX_TARGETS: &[&str] = &[
+    "aarch64-unknown-linux-gnu",
+    "aarch64-unknown-linux-musl",
+    "armv7-unknown-linux-gnueabihf",
+    "i686-unknown-linux-gnu",
+    "x86_64-unknown-linux-gnu",
+    "x86_64-unknown-linux-musl",
+];
+
+/// A channel's release manifest, describing one build: what's in it and
+/// which targets each package is available for.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Manifest {
+    /// The day the build was published.
+    pub date: NaiveDate,
+    /// The packages of the build by name, like `rustc` or `clippy-preview`.
+    #[serde(rename = "pkg")]
+    pub packages: HashMap<String, PackageTargets>,
+    /// The packages each rustup profile installs, by profile name.
+    pub profiles: HashMap<String, Vec<String>>,
+    /// The packages published under another name by the name they're asked
+    /// for as, missing from older manifests.
+    #[serde(default)]
+    pub renames: HashMap<String, Rename>,
+    /// SHA-256 of the manifest as downloaded.
+    #[serde(skip)]
+    pub sha256: String,
+    /// The Rust version as [`Options::version_pattern`] extracted it, if the
+    /// fetcher had one and it matched.
+    #[serde(skip)]
+    pub rust_version: Option<String>,
+}
+
+/// Where a renamed package is published now.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Rename {
+    /// The package's name in [`Manifest::packages`].
+    pub to: String,
+}
+
+/// One package of a build for every target it's built for.
+#[derive(Debug, Clone, Deserialize)]
+pub struct PackageTargets {
+    /// The package's version string, like `1.78.0 (9b00956e5 2024-04-29)`.
+    pub version: String,
+    /// The commit of rust-lang/rust the package was built from.
+    pub git_commit_hash: Option<String>,
+    /// The package for each target triple, or for `*` if it's the same for
+    /// every target.
+    #[serde(rename = "target")]
+    pub targets: HashMap<String, PackageInfo>,
+}
+
+/// One package of a build for one target.
+#[derive(Debug, Clone, Deserialize)]
+pub struct PackageInfo {
+    /// Whether it was built, as components fail to build on some days.
+    pub available: bool,
+}
+
+/// The server rustup installs from unless told otherwise.
+pub const DEFAULT_DIST_SERVER: &str = "https://static.rust-lang.org";
+
+/// Where a [`Fetcher`] gets manifests and how it keeps them. The defaults
+/// download from [`DEFAULT_DIST_SERVER`] and cache in the user's cache
+/// directory. Nothing is read from the environment, so a caller honoring
+/// `RUSTUP_DIST_SERVER` like rustup does sets [`dist_server`] from it.
+///
+/// [`dist_server`]: Self::dist_server
+#[derive(Debug, Clone)]
+pub struct Options {
+    /// The server to download manifests from, like a mirror of
+    /// [`DEFAULT_DIST_SERVER`].
+    pub dist_server: String,
+    /// Servers to try in order when downloading from `dist_server` fails.
+    /// The official server is always tried last.
+    pub fallback_dist_servers: Vec<String>,
+    /// Where manifests are cached, or `None` for the user's cache directory.
+    pub cache_dir: Option<PathBuf>,
+    /// Leaves the cache as it is instead of writing to it.
+    pub read_only: bool,
+    /// Reads the manifests of builds rustup installed instead of downloading
+    /// them again.
+    pub rustup_manifests: bool,
+    /// Extracts the Rust version from the `rust` package's version string
+    /// instead of taking the leading `x.y.z`, for forks with vendor suffixes
+    /// like `1.78.0-acme.3`. The first capture group is the version, or the
+    /// whole match if there is none.
+    pub version_pattern: Option<Regex>,
+    /// How manifests are downloaded.
+    pub http: http::Options,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            dist_server: DEFAULT_DIST_SERVER.to_owned(),
+            fallback_dist_servers: Vec::new(),
+            cache_dir: None,
+            read_only: false,
+            rustup_manifests: false,
+            version_pattern: None,
+            http: http::Options::default(),
+        }
+    }
+}
+
+impl Options {
+    /// Where the release manifests are published.
+    pub fn base_url(&self) -> String {
+        format!("{}/dist", self.dist_server.trim_end_matches('/'))
+    }
+
+    /// Where else the release manifests are published. The official server
+    /// is always the last resort for another main one.
+    fn fallback_base_urls(&self) -> Vec<String> {
+        let mut base_urls = vec![self.base_url()];
+        let servers = self
+            .fallback_dist_servers
+            .iter()
+            .map(String::as_str)
+            .chain(std::iter::once(DEFAULT_DIST_SERVER));
+        for server in servers {
+            let base_url = format!("{}/dist", server.trim_end_matches('/'));
+            if !base_urls.contains(&base_url) {
+                base_urls.push(base_url);
+            }
+        }
+        base_urls.remove(0);
+        base_urls
+    }
+
+    /// The URL of the latest manifest of `channel`.
+    pub fn manifest_url(&self, channel: &str) -> String {
+        format!("{}/channel-rust-{}.toml", self.base_url(), channel)
+    }
+
+    /// The URL of the manifest of `channel` published on `date`.
+    pub fn dated_manifest_url(&self, date: NaiveDate, channel: &str) -> String {
+        format!("{}/{}/channel-rust-{}.toml", self.base_url(), date, channel)
+    }
+}
+
+/// The SHA-256 published next to the file at `url`, if there is one.
+fn get_published_sha256(client: &Client, url: &str) -> Result<Option<String>> {
+    let url = format!("{}.sha256", url);
+    let content = match client.get(&url)? {
+        Some(content) => String::from_utf8_lossy(&content).into_owned(),
+        None => return Ok(None),
+    };
+    match content.split_whitespace().next() {
+        Some(sha256) => Ok(Some(sha256.to_ascii_lowercase())),
+        None => bail!("empty checksum file {}", url),
+    }
+}
+
+/// Splits the URL of a dated manifest under `base_url` into its date and
+/// channel.
+fn dated_manifest_url<'a>(
+    base_url: &str,
+    url: &'a str,
+) -> Option<(&'a str, &'a str)> {
+    let (date, name) = url
+        .strip_prefix(base_url)?
+        .strip_prefix('/')?
+        .split_once("/channel-rust-")?;
+    Some((date, name.strip_suffix(".toml")?))
+}
+
+/// Writes a file by renaming a temporary file into place, so concurrent runs
+/// never observe a partially written file.
+pub fn write_file(path: &Path, contents: impl AsRef<[u8]>) -> Result<()> {
+    let parent = path.parent().unwrap_or_else(|| Path::new("."));
+    std::fs::create_dir_all(parent)
+        .with_context(|| format!("error creating {}", parent.display()))?;
+    let mut temp_name = path.file_name().unwrap_or_default().to_owned();
+    temp_name.push(format!(".{}.tmp", std::process::id()));
+    let temp_path = path.with_file_name(temp_name);
+    std::fs::write(&temp_path, contents)
+        .and_then(|()| std::fs::rename(&temp_path, path))
+        .with_context(|| format!("error writing {}", path.display()))
+}
+
+/// Parses manifest `content` downloaded from `url`, checking it against the
+/// published SHA-256.
+fn parse_manifest(
+    client: &Client,
+    url: &str,
+    content: &[u8],
+) -> Result<Manifest> {
+    let sha256 = format!("{:x}", Sha256::digest(content));
+    if let Some(published) = get_published_sha256(client, url)? {
+        if published != sha256 {
+            bail!(
+                "manifest {} doesn't match its published SHA-256, the \
+                 download is corrupted or was tampered with",
+                url
+            );
+        }
+    }
+    let mut manifest: Manifest =
+        toml::from_slice(content).context("error reading latest manifest")?;
+    manifest.sha256 = sha256;
+    Ok(manifest)
+}
+
+/// How many manifests are downloaded at once while scanning a channel's
+/// history. Matches the idle connections the client keeps, so every download
+/// of a batch can reuse one.
+const MAX_CONCURRENT_FETCHES: usize = 8;
+
+/// Downloads manifests as its [`Options`] say, remembering the ones already
+/// fetched so that several queries in the same run don't download them
+/// twice, unless made [`forgetful`](Self::forgetful). Clones share the
+/// client, what was fetched and the diagnostics.
+#[derive(Clone)]
+pub struct Fetcher {
+    client: Arc<Client>,
+    options: Arc<Options>,
+    /// `None` if manifests are forgotten once handed out.
+    fetched: Option<Arc<Mutex<HashMap<String, Option<Manifest>>>>>,
+    diagnostics: Arc<Mutex<Vec<Diagnostic>>>,
+    /// The builds rustup installed, read on first use.
+    installed: Arc<Mutex<Option<Arc<rustup::InstalledBuilds>>>>,
+}
+
+impl Fetcher {
+    /// A fetcher with the default [`Options`].
+    pub fn new() -> Self {
+        Fetcher::with_options(Options::default())
+    }
+
+    /// A fetcher with `options`, whose HTTP options have to pass
+    /// [`http::Options::check`].
+    pub fn with_options(options: Options) -> Self {
+        Fetcher {
+            client: Arc::new(Client::new(&options.http)),
+            options: Arc::new(options),
+            fetched: Some(Default::default()),
+            diagnostics: Default::default(),
+            installed: Default::default(),
+        }
+    }
+
+    /// Forgets manifests once handed out, so a long scan that looks at each
+    /// manifest once doesn't keep them all in memory.
+    pub fn forgetful(mut self) -> Self {
+        self.fetched = None;
+        self
+    }
+
+    /// The options manifests are fetched with.
+    pub fn options(&self) -> &Options {
+        &self.options
+    }
+
+    /// Takes what the fetcher ran into so far that didn't stop it, oldest
+    /// first.
+    pub fn take_diagnostics(&self) -> Vec<Diagnostic> {
+        std::mem::take(&mut *self.diagnostics.lock().unwrap())
+    }
+
+    fn report(&self, diagnostic: Diagnostic) {
+        self.diagnostics.lock().unwrap().push(diagnostic);
+    }
+
+    /// The manifests at `urls` in the same order, downloading them in
+    /// parallel.
+    fn fetch_all(&self, urls: Vec<String>) -> Vec<Result<Option<Manifest>>> {
+        let downloads = urls
+            .into_iter()
+            .map(|url| {
+                let fetcher = self.clone();
+                let download = thread::spawn(move || fetcher.manifest(&url));
+                (url, download)
+            })
+            .collect::<Vec<_>>();
+        downloads
+            .into_iter()
+            .map(|(url, download)| {
+                download.join().unwrap_or_else(|_| {
+                    Err(anyhow!("download of {} panicked", url))
+                })
+            })
+            .collect()
+    }
+
+    /// The manifest at `url`, downloading it on first use, or `None` if it
+    /// doesn't exist.
+    pub fn manifest(&self, url: &str) -> Result<Option<Manifest>> {
+        let fetched = match &self.fetched {
+            Some(fetched) => fetched,
+            None => return self.get_manifest(url),
+        };
+        if let Some(manifest) = fetched.lock().unwrap().get(url) {
+            return Ok(manifest.clone());
+        }
+        let manifest = self.get_manifest(url)?;
+        fetched
+            .lock()
+            .unwrap()
+            .insert(url.to_owned(), manifest.clone());
+        Ok(manifest)
+    }
+
+    /// Forgets the latest manifests of channels, keeping the dated ones, which
+    /// never change. For long-running processes to notice new releases.
+    pub fn forget_latest(&self) {
+        if let Some(fetched) = &self.fetched {
+            let base_url = self.options.base_url();
+            fetched
+                .lock()
+                .unwrap()
+                .retain(|url, _| dated_manifest_url(&base_url, url).is_some());
+        }
+    }
+
+    /// The HTTP client manifests are downloaded with.
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+
+    /// The builds rustup installed, reading them on first use.
+    fn installed_builds(&self) -> Result<Arc<rustup::InstalledBuilds>> {
+        let mut installed = self.installed.lock().unwrap();
+        if let Some(builds) = &*installed {
+            return Ok(Arc::clone(builds));
+        }
+        let builds = Arc::new(rustup::InstalledBuilds::read()?);
+        *installed = Some(Arc::clone(&builds));
+        Ok(builds)
+    }
+
+    /// Reads the manifest at `url` from the cache or from rustup, or
+    /// downloads and parses it, giving `None` if it doesn't exist.
+    fn get_manifest(&self, url: &str) -> Result<Option<Manifest>> {
+        let mut manifest = match self.read_or_download_manifest(url)? {
+            Some(manifest) => manifest,
+            None => return Ok(None),
+        };
+        if let Some(pattern) = &self.options.version_pattern {
+            manifest.rust_version = manifest
+                .packages
+                .get("rust")
+                .and_then(|package| pattern.captures(&package.version))
+                .and_then(|captures| {
+                    captures.get(1).or_else(|| captures.get(0))
+                })
+                .map(|version| version.as_str().to_owned());
+        }
+        Ok(Some(manifest))
+    }
+
+    fn read_or_download_manifest(&self, url: &str) -> Result<Option<Manifest>> {
+        let options = &*self.options;
+        let offline = options.http.offline;
+        if let Some(content) = cache::read(options, url)? {
+            let mut manifest: Manifest = toml::from_slice(&content)
+                .context("error reading cached manifest")?;
+            // It was checked against the published SHA-256 when downloaded.
+            manifest.sha256 = format!("{:x}", Sha256::digest(&content));
+            return Ok(Some(manifest));
+        }
+        let base_url = options.base_url();
+        let dated = dated_manifest_url(&base_url, url);
+        let installed = match dated {
+            Some((date, channel)) if options.rustup_manifests => {
+                self.installed_builds()?.manifest(date, channel)?
+            },
+            _ => None,
+        };
+        if let Some(content) = installed {
+            let mut manifest: Manifest = toml::from_slice(&content)
+                .context("error reading rustup's manifest")?;
+            // Only the small checksum file is downloaded, so outputs that
+            // print the hash still give the published one.
+            let published = if offline {
+                None
+            } else {
+                get_published_sha256(&self.client, url)?
+            };
+            manifest.sha256 = published
+                .unwrap_or_else(|| format!("{:x}", Sha256::digest(&content)));
+            return Ok(Some(manifest));
+        }
+        // Offline, only a dated manifest recorded as missing is of a day
+        // without a release. Any other one would have to be downloaded to
+        // tell.
+        if offline {
+            if let Some((date, channel)) = dated {
+                if cache::is_missing(options, url) {
+                    return Ok(None);
+                }
+                bail!(
+                    "the {} manifest of {} isn't cached, so it can't be read \
+                     offline",
+                    channel,
+                    date
+                );
+            }
+        }
+
+        let mut result = self.download_manifest(url, url);
+        let path = match url.strip_prefix(base_url.as_str()) {
+            Some(path) if !offline => path,
+            _ => return result,
+        };
+        // A stale mirror doesn't have the newest manifests yet, so they're
+        // looked for on the next server too.
+        for fallback in options.fallback_base_urls() {
+            match &result {
+                Ok(Some(_)) => break,
+                Ok(None) => {},
+                Err(error) => self.report(Diagnostic::FallingBack {
+                    error: format!("{:#}", error),
+                    fallback: fallback.clone(),
+                }),
+            }
+            result =
+                self.download_manifest(url, &format!("{}{}", fallback, path));
+        }
+        if let Ok(None) = result {
+            if let Err(error) = cache::write_missing(options, url) {
+                self.report(Diagnostic::NotCached {
+                    url: url.to_owned(),
+                    error: format!("{:#}", error),
+                });
+            }
+        }
+        result
+    }
+
+    /// Downloads the manifest at `url`, caching it as the one at `cache_url`.
+    fn download_manifest(
+        &self,
+        cache_url: &str,
+        url: &str,
+    ) -> Result<Option<Manifest>> {
+        let options = &*self.options;
+        // Validators only mean something to the server that sent them, so a
+        // copy from a fallback server is cached without any.
+        let from_fallback = url != cache_url;
+        let (cached, validators) =
+            match cache::revalidation(options, cache_url)? {
+                Some((content, validators)) if !from_fallback => {
+                    (Some(content), validators)
+                },
+                _ => (None, Validators::default()),
+            };
+        let content = match self.client.get_conditional(url, &validators)? {
+            Conditional::Modified(content, validators) => {
+                // Only a download that passed the checks is cached, since
+                // the cache is trusted as is when read back.
+                let manifest = parse_manifest(&self.client, url, &content)?;
+                let validators = if from_fallback {
+                    Validators::default()
+                } else {
+                    validators
+                };
+                if let Err(error) =
+                    cache::write(options, cache_url, &content, &validators)
+                {
+                    self.report(Diagnostic::NotCached {
+                        url: cache_url.to_owned(),
+                        error: format!("{:#}", error),
+                    });
+                }
+                return Ok(Some(manifest));
+            },
+            Conditional::NotModified => match cached {
+                Some(content) => content,
+                None => bail!("{} wasn't modified, but isn't cached", url),
+            },
+            Conditional::NotFound => return Ok(None),
+        };
+        Ok(Some(parse_manifest(&self.client, url, &content)?))
+    }
+}
+
+impl Default for Fetcher {
+    fn default() -> Self {
+        Fetcher::new()
+    }
+}
+
+/// Whether every package of `profile` not in `ignored_packages` is available
+/// for all of `targets`.
+pub fn filter_manifest(
+    manifest: &Manifest,
+    profile: &[&str],
+    ignored_packages: &[&str],
+    targets: &[&str],
+) -> bool {
+    manifest
+        .packages
+        .iter()
+        .filter(|(package, _package_targets)| {
+            let package = package.as_str();
+            if ignored_packages.contains(&package) {
+                return false;
+            }
+            if !profile.contains(&package) {
+                return false;
+            }
+            true
+        })
+        .flat_map(|(_package, package_targets)| {
+            targets
+                .iter()
+                .filter_map(|&target| package_targets.targets.get(target))
+                .collect::<Vec<_>>()
+        })
+        .all(|package_info| package_info.available)
+}
+
+/// Which days before the channel's latest release to look at.
+pub trait DateWindow: std::fmt::Debug {
+    /// The days to look at given the date of the latest release, newest
+    /// first and all before it.
+    fn dates(&self, latest: NaiveDate) -> Box<dyn Iterator<Item = NaiveDate>>;
+}
+
+/// Every day within the given number of days of the latest release, counting
+/// the day of the latest release. This is the window of [`manifests`].
+#[derive(Debug, Clone, Copy)]
+pub struct Daily(pub usize);
+
+impl DateWindow for Daily {
+    fn dates(&self, latest: NaiveDate) -> Box<dyn Iterator<Item = NaiveDate>> {
+        Box::new((1..self.0).filter_map(move |day| {
+            latest.checked_sub_signed(Duration::days(day as i64))
+        }))
+    }
+}
+
+/// The first of each month within the given number of days of the latest
+/// release, like for archiving one build a month.
+#[derive(Debug, Clone, Copy)]
+pub struct FirstOfMonth(pub usize);
+
+impl DateWindow for FirstOfMonth {
+    fn dates(&self, latest: NaiveDate) -> Box<dyn Iterator<Item = NaiveDate>> {
+        Box::new(Daily(self.0).dates(latest).filter(|date| date.day() == 1))
+    }
+}
+
+/// Exactly the given days, in any order. Days after the latest release are
+/// left out.
+#[derive(Debug, Clone)]
+pub struct Dates(pub Vec<NaiveDate>);
+
+impl DateWindow for Dates {
+    fn dates(&self, latest: NaiveDate) -> Box<dyn Iterator<Item = NaiveDate>> {
+        let mut dates = self
+            .0
+            .iter()
+            .copied()
+            .filter(|&date| date < latest)
+            .collect::<Vec<_>>();
+        dates.sort_unstable_by(|a, b| b.cmp(a));
+        dates.dedup();
+        Box::new(dates.into_iter())
+    }
+}
+
+/// Every day after the given one, up to the latest release, like for
+/// looking at the builds newer than a pinned one.
+#[derive(Debug, Clone, Copy)]
+pub struct After(pub NaiveDate);
+
+impl DateWindow for After {
+    fn dates(&self, latest: NaiveDate) -> Box<dyn Iterator<Item = NaiveDate>> {
+        let days = latest.signed_duration_since(self.0).num_days().max(0);
+        Daily(days as usize).dates(latest)
+    }
+}
+
+/// The channel's latest manifest followed by the manifests of each earlier day
+/// within `max_age` days of it, leaving out days without a release and
+/// `skip_dates`.
+pub fn manifests<'a>(
+    fetcher: &'a Fetcher,
+    channel: &'a str,
+    max_age: usize,
+    skip_dates: &'a [NaiveDate],
+) -> Result<impl Iterator<Item = Result<Manifest>> + 'a> {
+    manifests_in(fetcher, channel, &Daily(max_age), skip_dates)
+}
+
+/// Like [`manifests`], looking at the days of `window` instead of every day.
+pub fn manifests_in<'a>(
+    fetcher: &'a Fetcher,
+    channel: &'a str,
+    window: &dyn DateWindow,
+    skip_dates: &'a [NaiveDate],
+) -> Result<impl Iterator<Item = Result<Manifest>> + 'a> {
+    let options = fetcher.options();
+    let latest_manifest =
+        match fetcher.manifest(&options.manifest_url(channel))? {
+            Some(manifest) => manifest,
+            None => bail!("no manifest found for release channel {}", channel),
+        };
+
+    let mut dates = window
+        .dates(latest_manifest.date)
+        .filter(move |date| !skip_dates.contains(date));
+    // Days are downloaded a batch at a time, so a search ending early
+    // downloads at most one batch more than it needs.
+    let batches = std::iter::from_fn(move || {
+        let urls = dates
+            .by_ref()
+            .take(MAX_CONCURRENT_FETCHES)
+            .map(|date| options.dated_manifest_url(date, channel))
+            .collect::<Vec<_>>();
+        if urls.is_empty() {
+            None
+        } else {
+            Some(urls)
+        }
+    });
+
+    let mut manifests = std::iter::once(Ok(latest_manifest))
+        .chain(
+            batches
+                .flat_map(move |urls| fetcher.fetch_all(urls))
+                .filter_map(Result::transpose),
+        )
+        .filter(move |manifest| {
+            manifest
+                .as_ref()
+                .map_or(true, |manifest| !skip_dates.contains(&manifest.date))
+        })
+        .peekable();
+    // A respun release can be published again the next day with the same
+    // date, so only one of the manifests sharing a date is kept: the one with
+    // the highest version, or the later one if they have the same version.
+    Ok(std::iter::from_fn(move || {
+        let mut manifest = match manifests.next()? {
+            Ok(manifest) => manifest,
+            Err(error) => return Some(Err(error)),
+        };
+        while let Some(Ok(respin)) = manifests.next_if(
+            |next| matches!(next, Ok(next) if next.date == manifest.date),
+        ) {
+            let (kept, dropped) = choose_respin(manifest, respin);
+            fetcher.report(Diagnostic::Respin {
+                channel: channel.to_owned(),
+                date: kept.date,
+                kept: describe_respin(&kept),
+                dropped: describe_respin(&dropped),
+            });
+            manifest = kept;
+        }
+        Some(Ok(manifest))
+    }))
+}
+
+/// Which of two manifests sharing a date to keep, giving the kept one first.
+/// That's the one with the higher version, or `first` if they have the same
+/// version, as manifests come newest first.
+fn choose_respin(first: Manifest, second: Manifest) -> (Manifest, Manifest) {
+    if version_key(&second) > version_key(&first) {
+        (second, first)
+    } else {
+        (first, second)
+    }
+}
+
+/// Tells a respun manifest apart from another one of its date by its version
+/// and hash.
+fn describe_respin(manifest: &Manifest) -> String {
+    let sha256 = manifest.sha256.get(..12).unwrap_or(&manifest.sha256);
+    format!(
+        "{} ({})",
+        get_rust_version(manifest)
+            .unwrap_or_else(|| "unknown version".to_owned()),
+        sha256
+    )
+}
+
+/// The numeric parts of the manifest's Rust version, for comparing them.
+fn version_key(manifest: &Manifest) -> Vec<u64> {
+    get_rust_version(manifest)
+        .unwrap_or_default()
+        .split('.')
+        .filter_map(|part| part.parse().ok())
+        .collect()
+}
+
+/// The newest manifest of `channel` within `max_age` days of its latest
+/// release that `is_viable` accepts.
+pub fn find_latest_viable_manifest(
+    fetcher: &Fetcher,
+    channel: &str,
+    max_age: usize,
+    skip_dates: &[NaiveDate],
+    is_viable: impl Fn(&Manifest) -> bool,
+) -> Result<Option<Manifest>> {
+    manifests(fetcher, channel, max_age, skip_dates)?
+        .find(|manifest| manifest.as_ref().map_or(true, &is_viable))
+        .transpose()
+}
+
+/// Components that were published under a `-preview` name before being
+/// stabilized. Manifests from before the renames table existed only know one
+/// of the two names.
+static PREVIEW_NAMES: &[(&str, &str)] = &[
+    ("clippy", "clippy-preview"),
+    ("llvm-tools", "llvm-tools-preview"),
+    ("miri", "miri-preview"),
+    ("rls", "rls-preview"),
+    ("rust-analyzer", "rust-analyzer-preview"),
+    ("rustc-codegen-cranelift", "rustc-codegen-cranelift-preview"),
+    ("rustfmt", "rustfmt-preview"),
+];
+
+/// Looks up the package for `component`, following the manifest's renames
+/// and then trying the component's other name from [`PREVIEW_NAMES`].
+pub fn find_package<'a>(
+    manifest: &'a Manifest,
+    component: &str,
+) -> Option<&'a PackageTargets> {
+    let name = manifest
+        .renames
+        .get(component)
+        .map_or(component, |rename| rename.to.as_str());
+    manifest.packages.get(name).or_else(|| {
+        let other_name =
+            PREVIEW_NAMES.iter().find_map(|&(stable, preview)| {
+                if name == stable {
+                    Some(preview)
+                } else if name == preview {
+                    Some(stable)
+                } else {
+                    None
+                }
+            })?;
+        manifest.packages.get(other_name)
+    })
+}
+
+/// Whether every one of `components` is available for all of `targets`.
+pub fn has_components(
+    manifest: &Manifest,
+    components: &[String],
+    targets: &[&str],
+) -> bool {
+    components.iter().all(|component| {
+        find_package(manifest, component).map_or(false, |package_targets| {
+            targets.iter().all(|&target| {
+                package_targets
+                    .targets
+                    .get(target)
+                    .or_else(|| package_targets.targets.get("*"))
+                    .map_or(false, |package_info| package_info.available)
+            })
+        })
+    })
+}
+
+/// The leading `x.y.z` of a package's version string.
+pub fn package_version(package_targets: &PackageTargets) -> Option<&str> {
+    let captures = Regex::new(r#"^(\d+\.\d+\.\d+)"#)
+        .unwrap()
+        .captures(&package_targets.version)?;
+    Some(captures.get(1)?.as_str())
+}
+
+/// The Rust version the manifest's build is for, like `1.34.1`: the one
+/// [`Options::version_pattern`] extracted, or else the leading `x.y.z` of the
+/// `rust` package's version.
+pub fn get_rust_version(manifest: &Manifest) -> Option<String> {
+    if let Some(version) = &manifest.rust_version {
+        return Some(version.clone());
+    }
+    let package = manifest.packages.get("rust")?;
+    let version = package_version(package)?;
+    Some(version.to_string())
+}
+
+/// The name rustup knows the manifest's toolchain by, giving the version for
+/// stable releases unless `force_date` is set.
+pub fn make_toolchain_name(
+    manifest: &Manifest,
+    channel: &str,
+    force_date: bool,
+) -> String {
+    if !force_date && channel == "stable" {
+        if let Some(version) = get_rust_version(manifest) {
+            return version;
+        }
+    }
+
+    format!("{}-{}", channel, manifest.date)
+}
+
+/// Builder for looking up the latest build of a channel that has everything
+/// a project needs.
+#[derive(Debug, Clone)]
+pub struct ToolchainFinder {
+    channel: String,
+    profile: String,
+    components: Vec<String>,
+    ignored_packages: Vec<String>,
+    targets: Vec<String>,
+    window: Arc<dyn DateWindow + Send + Sync>,
+    skip_dates: Vec<NaiveDate>,
+    options: Options,
+}
+
+impl ToolchainFinder {
+    /// Looks up builds of `channel`, like `nightly`, with the `default`
+    /// profile for the target this crate was built for, going back up to 90
+    /// days, with the default [`Options`].
+    pub fn new(channel: &str) -> Self {
+        ToolchainFinder {
+            channel: channel.to_owned(),
+            profile: "default".to_owned(),
+            components: Vec::new(),
+            ignored_packages: Vec::new(),
+            targets: vec![CURRENT_TARGET.to_owned()],
+            window: Arc::new(Daily(90)),
+            skip_dates: Vec::new(),
+            options: Options::default(),
+        }
+    }
+
+    /// Requires every package of the rustup profile `profile`, like
+    /// `minimal` or `complete`.
+    pub fn profile(mut self, profile: &str) -> Self {
+        self.profile = profile.to_owned();
+        self
+    }
+
+    /// Also requires `components`, which can be outside the profile.
+    pub fn components(mut self, components: &[&str]) -> Self {
+        self.components
+            .extend(components.iter().map(|&component| component.to_owned()));
+        self
+    }
+
+    /// Doesn't require the packages of the profile in `packages`, like the
+    /// `rust-docs` that some days are missing.
+    pub fn ignore_packages(mut self, packages: &[&str]) -> Self {
+        self.ignored_packages
+            .extend(packages.iter().map(|&package| package.to_owned()));
+        self
+    }
+
+    /// Requires everything for all of `targets` instead of the current one.
+    pub fn targets(mut self, targets: &[&str]) -> Self {
+        self.targets =
+            targets.iter().map(|&target| target.to_owned()).collect();
+        self
+    }
+
+    /// How many days before the channel's latest release to look back.
+    pub fn max_age(mut self, days: usize) -> Self {
+        self.window = Arc::new(Daily(days));
+        self
+    }
+
+    /// Looks at the days of `window` instead of every day, replacing
+    /// [`max_age`](Self::max_age).
+    pub fn window(
+        mut self,
+        window: impl DateWindow + Send + Sync + 'static,
+    ) -> Self {
+        self.window = Arc::new(window);
+        self
+    }
+
+    /// Never selects the builds of `dates`.
+    pub fn skip_dates(mut self, dates: &[NaiveDate]) -> Self {
+        self.skip_dates.extend_from_slice(dates);
+        self
+    }
+
+    /// Fetches manifests with `options` in [`find`](Self::find).
+    pub fn options(mut self, options: Options) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// The manifest of the newest viable build, if there is one. What a
+    /// fetcher would give as diagnostics is dropped, so use
+    /// [`find_with`](Self::find_with) to see them.
+    pub fn find(&self) -> Result<Option<Manifest>> {
+        self.options.http.check()?;
+        self.find_with(&Fetcher::with_options(self.options.clone()))
+    }
+
+    /// Like [`find`](Self::find), reusing the manifests `fetcher` already
+    /// downloaded and fetching with its options instead.
+    pub fn find_with(&self, fetcher: &Fetcher) -> Result<Option<Manifest>> {
+        self.viable_manifests(fetcher)?.next().transpose()
+    }
+
+    /// Every viable build, newest first, downloading manifests only as the
+    /// iterator advances. For choosing a build some other way than taking
+    /// the newest, like skipping builds that have been out less than a week.
+    pub fn viable_manifests<'a>(
+        &'a self,
+        fetcher: &'a Fetcher,
+    ) -> Result<impl Iterator<Item = Result<Manifest>> + 'a> {
+        Ok(manifests_in(
+            fetcher,
+            &self.channel,
+            &*self.window,
+            &self.skip_dates,
+        )?
+        .filter(move |manifest| {
+            manifest
+                .as_ref()
+                .map_or(true, |manifest| self.is_viable(manifest))
+        }))
+    }
+
+    fn is_viable(&self, manifest: &Manifest) -> bool {
+        let targets =
+            self.targets.iter().map(String::as_str).collect::<Vec<_>>();
+        let profile = match manifest.profiles.get(&self.profile) {
+            Some(profile) => profile,
+            None => return false,
+        };
+        let profile = profile.iter().map(String::as_str).collect::<Vec<_>>();
+        let ignored_packages = self
+            .ignored_packages
+            .iter()
+            .map(String::as_str)
+            .collect::<Vec<_>>();
+        filter_manifest(manifest, &profile, &ignored_packages, &targets)
+            && has_components(manifest, &self.components, &targets)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manifest(version: &str, sha256: &str) -> Manifest {
+        let mut manifest: Manifest = toml::from_str(&format!(
+            r#"
+            date = "2024-05-02"
+            [pkg.rust]
+            version = "{} (abcdef012 2024-05-01)"
+            [pkg.rust.target]
+            [profiles]
+            "#,
+            version
+        ))
+        .unwrap();
+        manifest.sha256 = sha256.to_owned();
+        manifest
+    }
+
+    #[test]
+    fn respin_with_higher_version_is_kept() {
+        let (kept, dropped) =
+            choose_respin(manifest("1.78.0", "a"), manifest("1.78.1", "b"));
+        assert_eq!(kept.sha256, "b");
+        assert_eq!(dropped.sha256, "a");
+    }
+
+    #[test]
+    fn respin_with_lower_version_is_dropped() {
+        let (kept, _) =
+            choose_respin(manifest("1.78.1", "a"), manifest("1.78.0", "b"));
+        assert_eq!(kept.sha256, "a");
+    }
+
+    #[test]
+    fn respin_with_same_version_keeps_the_later_one() {
+        let (kept, dropped) =
+            choose_respin(manifest("1.78.0", "a"), manifest("1.78.0", "b"));
+        assert_eq!(kept.sha256, "a");
+        assert_eq!(dropped.sha256, "b");
+    }
+
+    #[test]
+    fn respins_are_described_by_version_and_hash() {
+        let manifest = manifest("1.78.0", "0123456789abcdef");
+        assert_eq!(describe_respin(&manifest), "1.78.0 (0123456789ab)");
+    }
+
+    #[test]
+    fn ignored_packages_are_not_required() {
+        let manifest: Manifest = toml::from_str(
+            r#"
+            date = "2024-05-02"
+            [pkg.rustc]
+            version = "1.78.0 (abcdef012 2024-05-01)"
+            [pkg.rustc.target.x86_64-unknown-linux-gnu]
+            available = true
+            [pkg.rust-docs]
+            version = "1.78.0 (abcdef012 2024-05-01)"
+            [pkg.rust-docs.target.x86_64-unknown-linux-gnu]
+            available = false
+            [profiles]
+            default = ["rustc", "rust-docs"]
+            "#,
+        )
+        .unwrap();
+        let finder = ToolchainFinder::new("stable")
+            .targets(&["x86_64-unknown-linux-gnu"]);
+        assert!(!finder.is_viable(&manifest));
+        assert!(finder.ignore_packages(&["rust-docs"]).is_viable(&manifest));
+    }
+
+    #[test]
+    fn official_server_is_the_last_fallback() {
+        let options = Options {
+            dist_server: "https://mirror.example/".to_owned(),
+            fallback_dist_servers: vec![
+                "https://other.example".to_owned(),
+                "https://mirror.example".to_owned(),
+            ],
+            ..Options::default()
+        };
+        assert_eq!(options.base_url(), "https://mirror.example/dist");
+        assert_eq!(
+            options.fallback_base_urls(),
+            vec![
+                "https://other.example/dist",
+                "https://static.rust-lang.org/dist",
+            ]
+        );
+        assert!(Options::default().fallback_base_urls().is_empty());
+    }
+
+    #[test]
+    fn window_after_stops_at_the_day_after() {
+        let date = |day| NaiveDate::from_ymd(2024, 5, day);
+        let dates = After(date(2)).dates(date(5)).collect::<Vec<_>>();
+        assert_eq!(dates, vec![date(4), date(3)]);
+        assert_eq!(After(date(5)).dates(date(5)).count(), 0);
+        assert_eq!(After(date(6)).dates(date(5)).count(), 0);
+    }
+}
@@ -0,0 +1,52 @@
+//! Listing which stable minor releases can be installed with everything a
+//! query requires, to help choose an MSRV.
+
+use crate::{
+    get_rust_version,
+    toolchain_file::parse_version,
+    Query,
+    Requirements,
+    Session,
+};
+use anyhow::{bail, Context, Result};
+
+pub fn run(session: &Session, query: &Query, oldest: &str) -> Result<()> {
+    let requirements = Requirements::new(session, query)?;
+    let oldest_minor = match parse_version(oldest)[..] {
+        [1, minor, ..] => minor,
+        _ => bail!("expected a version like 1.56, got {}", oldest),
+    };
+
+    let fetcher = &session.fetcher;
+    let latest = fetcher
+        .manifest(&fetcher.options().manifest_url("stable"))?
+        .context("no manifest found for release channel stable")?;
+    let latest_version = get_rust_version(&latest)
+        .context("latest stable manifest has no rust version")?;
+    let latest_minor = match parse_version(&latest_version)[..] {
+        [1, minor, ..] => minor,
+        _ => bail!("unexpected stable version {}", latest_version),
+    };
+
+    for minor in (oldest_minor..=latest_minor).rev() {
+        let url = fetcher.options().manifest_url(&format!("1.{}", minor));
+        let manifest = match fetcher.manifest(&url)? {
+            Some(manifest) => manifest,
+            None => break,
+        };
+        let version = get_rust_version(&manifest)
+            .unwrap_or_else(|| format!("1.{}", minor));
+        let status = if requirements.is_viable(&manifest) {
+            "complete"
+        } else {
+            "incomplete"
+        };
+        println!(
+            "{:<8} {} {}",
+            version,
+            session.human_date(manifest.date),
+            status
+        );
+    }
+    Ok(())
+}
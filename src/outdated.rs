@@ -0,0 +1,52 @@
+//! Telling whether the toolchains rustup installed for a channel are behind
+//! its latest viable build.
+
+use crate::{
+    find_viable_manifest,
+    make_toolchain_name,
+    rustup,
+    Query,
+    Session,
+};
+use anyhow::Result;
+
+pub fn run(session: &Session, query: &Query) -> Result<()> {
+    let toolchains = rustup::toolchains()?
+        .into_iter()
+        .filter(|toolchain| toolchain.channel() == query.channel)
+        .collect::<Vec<_>>();
+    if toolchains.is_empty() {
+        println!("no {} toolchain is installed", query.channel);
+        return Ok(());
+    }
+
+    let latest = find_viable_manifest(session, query)?;
+    let latest_name =
+        make_toolchain_name(&latest, &query.channel, query.force_date);
+    for toolchain in toolchains {
+        let date = match toolchain.date()? {
+            Some(date) => date,
+            None => {
+                println!("{} has no manifest to date it by", toolchain.name);
+                continue;
+            },
+        };
+        let days = latest.date.signed_duration_since(date).num_days();
+        if days > 0 {
+            println!(
+                "{} from {} can be updated to {}, {} days newer",
+                toolchain.name,
+                session.human_date(date),
+                latest_name,
+                days
+            );
+        } else {
+            println!(
+                "{} from {} is up to date",
+                toolchain.name,
+                session.human_date(date)
+            );
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,296 @@
+//! Rendering the selected toolchain in the formats other tools consume.
+
+use crate::{
+    docker,
+    get_rust_version,
+    make_toolchain_name,
+    Manifest,
+    OutputOpt,
+    Query,
+    Requirements,
+    Session,
+};
+use anyhow::{bail, Result};
+use chrono::NaiveDate;
+use serde::Serialize;
+
+/// Quotes `s` as a Nix string literal.
+fn nix_string(s: &str) -> String {
+    let escaped = s
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace("${", "\\${");
+    format!("\"{}\"", escaped)
+}
+
+/// A Nix attrset describing the toolchain, pointing at the dated manifest so
+/// the URL and hash stay valid after the channel moves on.
+fn nix(
+    session: &Session,
+    query: &Query,
+    manifest: &Manifest,
+    latest_release_date: NaiveDate,
+) -> String {
+    let toolchain =
+        make_toolchain_name(manifest, &query.channel, query.force_date);
+    let url = session
+        .fetcher
+        .options()
+        .dated_manifest_url(manifest.date, &query.channel);
+    let version = get_rust_version(manifest)
+        .map_or_else(|| "null".to_owned(), |version| nix_string(&version));
+    let attrs = [
+        ("toolchain", nix_string(&toolchain)),
+        ("channel", nix_string(&query.channel)),
+        ("date", nix_string(&manifest.date.to_string())),
+        ("selected_date", nix_string(&manifest.date.to_string())),
+        (
+            "latest_release_date",
+            nix_string(&latest_release_date.to_string()),
+        ),
+        ("version", version),
+        ("url", nix_string(&url)),
+        ("sha256", nix_string(&manifest.sha256)),
+    ];
+    let body = attrs
+        .iter()
+        .map(|(name, value)| format!("  {} = {};\n", name, value))
+        .collect::<String>();
+    format!("{{\n{}}}", body)
+}
+
+/// The version string `rules_rust` expects in
+/// `rust_register_toolchains(versions = [...])`.
+fn bazel(query: &Query, manifest: &Manifest) -> String {
+    match (query.channel.as_str(), get_rust_version(manifest)) {
+        ("stable", Some(version)) => version,
+        (channel, _) => format!("{}/{}", channel, manifest.date),
+    }
+}
+
+#[derive(Serialize)]
+struct ToolchainFile<'a> {
+    toolchain: ToolchainSection<'a>,
+}
+
+#[derive(Serialize)]
+struct ToolchainSection<'a> {
+    channel: String,
+    profile: &'a str,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    components: Vec<&'a str>,
+    targets: Vec<&'a str>,
+}
+
+/// The official image of the stable release pinned by digest for each
+/// platform, one `<platform> rust:<version>@<digest>` line each.
+fn docker_manifest(
+    session: &Session,
+    query: &Query,
+    manifest: &Manifest,
+) -> Result<String> {
+    let version = match get_rust_version(manifest) {
+        Some(version) if query.channel == "stable" => version,
+        _ => bail!("official images are only tagged with stable versions"),
+    };
+    let lines = docker::digests(session.fetcher.client(), &version)?
+        .into_iter()
+        .map(|(platform, digest)| {
+            format!("{} rust:{}@{}", platform, version, digest)
+        })
+        .collect::<Vec<_>>();
+    Ok(lines.join("\n"))
+}
+
+/// A complete `rust-toolchain.toml` pinning the toolchain along with the
+/// components and targets it was selected for.
+fn toolchain_file(
+    query: &Query,
+    requirements: &Requirements,
+    manifest: &Manifest,
+) -> Result<String> {
+    let file = ToolchainFile {
+        toolchain: ToolchainSection {
+            channel: make_toolchain_name(
+                manifest,
+                &query.channel,
+                query.force_date,
+            ),
+            profile: requirements.profile.name(),
+            components: requirements.components(),
+            targets: requirements.targets(),
+        },
+    };
+    Ok(toml::to_string(&file)?.trim_end().to_owned())
+}
+
+#[derive(Serialize)]
+struct Json<'a> {
+    toolchain: String,
+    channel: &'a str,
+    date: NaiveDate,
+    selected_date: NaiveDate,
+    latest_release_date: NaiveDate,
+    version: Option<String>,
+    profile: &'a str,
+    targets: Vec<&'a str>,
+    url: String,
+    sha256: &'a str,
+}
+
+fn json(
+    session: &Session,
+    query: &Query,
+    requirements: &Requirements,
+    manifest: &Manifest,
+    latest_release_date: NaiveDate,
+) -> Result<String> {
+    let json = Json {
+        toolchain: make_toolchain_name(
+            manifest,
+            &query.channel,
+            query.force_date,
+        ),
+        channel: &query.channel,
+        date: manifest.date,
+        selected_date: manifest.date,
+        latest_release_date,
+        version: get_rust_version(manifest),
+        profile: requirements.profile.name(),
+        targets: requirements.targets(),
+        url: session
+            .fetcher
+            .options()
+            .dated_manifest_url(manifest.date, &query.channel),
+        sha256: &manifest.sha256,
+    };
+    Ok(serde_json::to_string(&json)?)
+}
+
+/// One `key value` line per field. Keys are only ever added, never changed
+/// or removed, and a key with several values is repeated.
+fn porcelain(
+    session: &Session,
+    query: &Query,
+    requirements: &Requirements,
+    manifest: &Manifest,
+    latest_release_date: NaiveDate,
+) -> String {
+    let mut lines = vec![
+        (
+            "toolchain",
+            make_toolchain_name(manifest, &query.channel, query.force_date),
+        ),
+        ("channel", query.channel.clone()),
+        ("date", manifest.date.to_string()),
+        ("latest-release-date", latest_release_date.to_string()),
+    ];
+    if let Some(version) = get_rust_version(manifest) {
+        lines.push(("version", version));
+    }
+    lines.push(("profile", requirements.profile.name().to_owned()));
+    for target in requirements.targets() {
+        lines.push(("target", target.to_owned()));
+    }
+    lines.push((
+        "url",
+        session
+            .fetcher
+            .options()
+            .dated_manifest_url(manifest.date, &query.channel),
+    ));
+    lines.push(("sha256", manifest.sha256.clone()));
+    lines
+        .iter()
+        .map(|(key, value)| format!("{} {}", key, value))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Expands the placeholders of a `--format` template. Builds without a
+/// known version print an empty `{version}`.
+fn template(
+    query: &Query,
+    manifest: &Manifest,
+    format: &str,
+) -> Result<String> {
+    let mut rendered = String::new();
+    let mut chars = format.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' => {
+                let rest = chars.as_str();
+                if let Some(rest) = rest.strip_prefix('{') {
+                    rendered.push('{');
+                    chars = rest.chars();
+                    continue;
+                }
+                let end = match rest.find('}') {
+                    Some(end) => end,
+                    None => bail!("unclosed placeholder in {:?}", format),
+                };
+                match &rest[..end] {
+                    "toolchain" => rendered.push_str(&make_toolchain_name(
+                        manifest,
+                        &query.channel,
+                        query.force_date,
+                    )),
+                    "channel" => rendered.push_str(&query.channel),
+                    "date" => rendered.push_str(&manifest.date.to_string()),
+                    "version" => rendered.push_str(
+                        &get_rust_version(manifest).unwrap_or_default(),
+                    ),
+                    placeholder => {
+                        bail!("unknown placeholder {{{}}}", placeholder)
+                    },
+                }
+                chars = rest[end + 1..].chars();
+            },
+            '}' => {
+                let rest = chars.as_str();
+                match rest.strip_prefix('}') {
+                    Some(rest) => chars = rest.chars(),
+                    None => bail!("unmatched }} in {:?}", format),
+                }
+                rendered.push('}');
+            },
+            c => rendered.push(c),
+        }
+    }
+    Ok(rendered)
+}
+
+pub fn render(
+    session: &Session,
+    query: &Query,
+    requirements: &Requirements,
+    manifest: &Manifest,
+    latest_release_date: NaiveDate,
+) -> Result<String> {
+    if let Some(format) = &query.format {
+        return template(query, manifest, format);
+    }
+    if query.porcelain {
+        return Ok(porcelain(
+            session,
+            query,
+            requirements,
+            manifest,
+            latest_release_date,
+        ));
+    }
+    Ok(match query.output {
+        OutputOpt::Plain => {
+            make_toolchain_name(manifest, &query.channel, query.force_date)
+        },
+        OutputOpt::Nix => nix(session, query, manifest, latest_release_date),
+        OutputOpt::Bazel => bazel(query, manifest),
+        OutputOpt::Json => {
+            json(session, query, requirements, manifest, latest_release_date)?
+        },
+        OutputOpt::ToolchainFileStdout => {
+            toolchain_file(query, requirements, manifest)?
+        },
+        OutputOpt::DockerManifest => docker_manifest(session, query, manifest)?,
+    })
+}
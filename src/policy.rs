@@ -0,0 +1,145 @@
+//! Organization-wide constraints on which builds may be selected.
+//!
+//! A policy is a TOML file, read from a local path or fetched from a URL:
+//!
+//! ```toml
+//! allowed-channels = ["stable", "nightly"]
+//! deny-dates = ["2024-06-01"]
+//! deny-versions = ["1.78.0"]
+//! min-soak-days = 3
+//! required-components = ["clippy", "rustfmt"]
+//!
+//! [budget.nightly]
+//! max-age-days = 30
+//!
+//! [budget.stable]
+//! max-releases-behind = 1
+//! ```
+//!
+//! Budgets don't affect resolution; they are checked against existing pins by
+//! `rust-latest audit`.
+//!
+//! When a public key is given, the policy must be accompanied by a minisign
+//! signature at the same location with `.minisig` appended.
+
+use crate::{get_rust_version, http::Client, Manifest};
+use anyhow::{bail, Context, Result};
+use chrono::{Duration, NaiveDate, Utc};
+#[cfg(feature = "signing")]
+use minisign_verify::{PublicKey, Signature};
+use serde::Deserialize;
+use std::collections::HashMap;
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct Policy {
+    pub allowed_channels: Option<Vec<String>>,
+    pub deny_dates: Vec<NaiveDate>,
+    pub deny_versions: Vec<String>,
+    pub min_soak_days: Option<u32>,
+    pub required_components: Vec<String>,
+    pub budget: HashMap<String, Budget>,
+}
+
+/// How far a pinned toolchain of one channel may fall behind.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct Budget {
+    pub max_age_days: Option<u32>,
+    pub max_releases_behind: Option<u32>,
+}
+
+fn is_url(location: &str) -> bool {
+    location.starts_with("https://") || location.starts_with("http://")
+}
+
+fn read_location(client: &Client, location: &str) -> Result<Vec<u8>> {
+    if is_url(location) {
+        match client.get(location)? {
+            Some(content) => Ok(content),
+            None => bail!("{} not found", location),
+        }
+    } else {
+        std::fs::read(location)
+            .with_context(|| format!("error reading {}", location))
+    }
+}
+
+/// Verifies `content` against the minisign signature published next to it
+/// at `<location>.minisig`.
+#[cfg(feature = "signing")]
+fn verify(
+    client: &Client,
+    location: &str,
+    content: &[u8],
+    public_key: &str,
+) -> Result<()> {
+    let public_key = PublicKey::from_base64(public_key)
+        .context("error parsing policy public key")?;
+    let signature_location = format!("{}.minisig", location);
+    let signature = read_location(client, &signature_location)?;
+    let signature = String::from_utf8(signature)
+        .map_err(anyhow::Error::from)
+        .and_then(|signature| Ok(Signature::decode(&signature)?))
+        .with_context(|| {
+            format!("error parsing signature {}", signature_location)
+        })?;
+    public_key
+        .verify(content, &signature, false)
+        .with_context(|| format!("invalid signature for policy {}", location))
+}
+
+#[cfg(not(feature = "signing"))]
+fn verify(
+    _client: &Client,
+    _location: &str,
+    _content: &[u8],
+    _public_key: &str,
+) -> Result<()> {
+    bail!("verifying policy signatures requires the signing feature")
+}
+
+pub fn load(
+    client: &Client,
+    location: &str,
+    public_key: Option<&str>,
+) -> Result<Policy> {
+    let content = read_location(client, location)?;
+    if let Some(public_key) = public_key {
+        verify(client, location, &content, public_key)?;
+    }
+    toml::from_slice(&content)
+        .with_context(|| format!("error parsing policy {}", location))
+}
+
+impl Policy {
+    pub fn check_channel(&self, channel: &str) -> Result<()> {
+        if let Some(allowed_channels) = &self.allowed_channels {
+            if !allowed_channels.iter().any(|allowed| allowed == channel) {
+                bail!("release channel {} is not allowed by policy", channel);
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether `manifest` satisfies the policy's per-build rules. Denied dates
+    /// are handled by the search itself so they are never downloaded, and
+    /// required components are checked along with the query's own.
+    pub fn allows(&self, manifest: &Manifest) -> bool {
+        if let Some(min_soak_days) = self.min_soak_days {
+            let soaked_until =
+                manifest.date + Duration::days(i64::from(min_soak_days));
+            if soaked_until > Utc::today().naive_utc() {
+                return false;
+            }
+        }
+        if !self.deny_versions.is_empty() {
+            if let Some(version) = get_rust_version(manifest) {
+                if self.deny_versions.contains(&version) {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+}
@@ -0,0 +1,116 @@
+//! A compact status string for embedding in shell prompts.
+//!
+//! The latest viable build of each channel is cached on disk so rendering the
+//! prompt only touches the network once the cached result is older than the
+//! configured threshold.
+
+use crate::{
+    find_viable_manifest,
+    get_rust_version,
+    toolchain_file::{self, parse_version, Pin},
+    Dirs,
+    Query,
+    Session,
+};
+use anyhow::{Context, Result};
+use chrono::{DateTime, NaiveDate, Utc};
+use clap::Parser;
+use serde::{Deserialize, Serialize};
+use std::{path::PathBuf, time::Duration};
+
+/// Describes how far behind `latest` the pin is, or `None` if it is up to
+/// date.
+fn status(pin: &Pin, latest: &Latest) -> Option<String> {
+    if let Some(date) = pin.date {
+        let days = latest.date.signed_duration_since(date).num_days();
+        if days <= 0 {
+            return None;
+        }
+        return Some(format!("⬆ {} {}d behind", pin.channel, days));
+    }
+    let pinned = parse_version(pin.version.as_ref()?);
+    let latest_version = latest.version.as_ref()?;
+    let mut newest = parse_version(latest_version);
+    newest.truncate(pinned.len());
+    if pinned >= newest {
+        return None;
+    }
+    Some(format!("⬆ {} {}", pin.channel, latest_version))
+}
+
+/// The cached latest viable build of a channel.
+#[derive(Debug, Serialize, Deserialize)]
+struct Latest {
+    checked_at: DateTime<Utc>,
+    date: NaiveDate,
+    version: Option<String>,
+}
+
+fn cache_path(dirs: &Dirs, channel: &str) -> Result<PathBuf> {
+    Ok(dirs.cache()?.join(format!("prompt-{}.toml", channel)))
+}
+
+fn read_cache(dirs: &Dirs, channel: &str) -> Result<Option<Latest>> {
+    let path = cache_path(dirs, channel)?;
+    match std::fs::read_to_string(&path) {
+        Ok(content) => Ok(toml::from_str(&content).ok()),
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(error) => Err(error)
+            .with_context(|| format!("error reading {}", path.display())),
+    }
+}
+
+fn write_cache(
+    session: &Session,
+    channel: &str,
+    latest: &Latest,
+) -> Result<()> {
+    session.write_file(
+        &cache_path(&session.dirs, channel)?,
+        toml::to_string(latest)?,
+    )
+}
+
+fn latest(
+    session: &Session,
+    channel: &str,
+    max_cache_age: Duration,
+) -> Result<Latest> {
+    if let Some(latest) = read_cache(&session.dirs, channel)? {
+        let age = Utc::now().signed_duration_since(latest.checked_at);
+        if age.to_std().map_or(true, |age| age < max_cache_age) {
+            return Ok(latest);
+        }
+    }
+
+    let mut query = Query::parse_from(&["rust-latest", "-t", "current"]);
+    query.channel = channel.to_owned();
+    let manifest = find_viable_manifest(session, &query)?;
+    let latest = Latest {
+        checked_at: Utc::now(),
+        date: manifest.date,
+        version: get_rust_version(&manifest),
+    };
+    if !session.read_only {
+        write_cache(session, channel, &latest)?;
+    }
+    Ok(latest)
+}
+
+pub fn run(session: &Session, max_cache_age: Duration) -> Result<()> {
+    let dir =
+        std::env::current_dir().context("error getting current directory")?;
+    let name = match toolchain_file::find(&dir) {
+        Some(path) => toolchain_file::read_channel(&path)?,
+        None => None,
+    };
+    let pin = match name.as_deref().and_then(Pin::parse) {
+        Some(pin) => pin,
+        None => return Ok(()),
+    };
+    let latest = latest(session, &pin.channel, max_cache_age)?;
+    if let Some(status) = status(&pin, &latest) {
+        println!("{}", status);
+    }
+    Ok(())
+}
@@ -0,0 +1,52 @@
+//! Resolving several named queries in one run.
+//!
+//! A query file maps names to the arguments of each query, in TOML
+//!
+//! ```toml
+//! tools = ["-c", "nightly", "-p", "minimal", "-t", "current"]
+//! release = ["-c", "stable"]
+//! ```
+//!
+//! or JSON (when the file name ends in `.json`). Manifests are shared between
+//! queries, so each one is only downloaded once. The results are printed in
+//! the same format as the file.
+
+use crate::{resolve, Query, Session};
+use anyhow::{Context, Result};
+use clap::Parser;
+use std::{collections::BTreeMap, path::Path};
+
+pub fn run(session: &Session, path: &Path) -> Result<()> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("error reading {}", path.display()))?;
+    let is_json = path.extension().map_or(false, |ext| ext == "json");
+    let queries = if is_json {
+        serde_json::from_str::<BTreeMap<String, Vec<String>>>(&content)
+            .map_err(anyhow::Error::from)
+    } else {
+        toml::from_str::<BTreeMap<String, Vec<String>>>(&content)
+            .map_err(anyhow::Error::from)
+    }
+    .with_context(|| format!("error parsing {}", path.display()))?;
+
+    let mut toolchains = BTreeMap::new();
+    for (name, args) in queries {
+        let toolchain = Query::try_parse_from(
+            std::iter::once("rust-latest".to_owned()).chain(args),
+        )
+        .map_err(anyhow::Error::from)
+        .and_then(|mut query| {
+            query.detect_project()?;
+            resolve(session, &query)
+        })
+        .with_context(|| format!("error resolving query {}", name))?;
+        toolchains.insert(name, toolchain);
+    }
+
+    if is_json {
+        println!("{}", serde_json::to_string_pretty(&toolchains)?);
+    } else {
+        print!("{}", toml::to_string(&toolchains)?);
+    }
+    Ok(())
+}
@@ -0,0 +1,172 @@
+//! Reading the defaults rustup is configured with, so answers match what
+//! `rustup toolchain install` would do on this machine, and the manifests of
+//! the toolchains it already installed.
+
+use anyhow::{Context, Result};
+use chrono::NaiveDate;
+use directories::BaseDirs;
+use serde::Deserialize;
+use std::{collections::HashMap, path::PathBuf};
+
+/// The parts of rustup's `settings.toml` that affect which components and
+/// targets get installed.
+#[derive(Debug, Default, Deserialize)]
+pub struct Settings {
+    pub default_host_triple: Option<String>,
+    pub profile: Option<String>,
+}
+
+fn rustup_home() -> Option<PathBuf> {
+    if let Some(home) = std::env::var_os("RUSTUP_HOME") {
+        return Some(PathBuf::from(home));
+    }
+    let base_dirs = BaseDirs::new()?;
+    Some(base_dirs.home_dir().join(".rustup"))
+}
+
+fn settings_path() -> Option<PathBuf> {
+    Some(rustup_home()?.join("settings.toml"))
+}
+
+/// Reads rustup's settings, which are all unset if rustup isn't installed.
+pub fn settings() -> Result<Settings> {
+    let path = match settings_path() {
+        Some(path) => path,
+        None => return Ok(Settings::default()),
+    };
+    match std::fs::read_to_string(&path) {
+        Ok(content) => toml::from_str(&content)
+            .with_context(|| format!("error parsing {}", path.display())),
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+            Ok(Settings::default())
+        },
+        Err(error) => Err(error)
+            .with_context(|| format!("error reading {}", path.display())),
+    }
+}
+
+/// A toolchain rustup installed, named like `nightly-x86_64-unknown-linux-gnu`
+/// or `nightly-2024-05-20-x86_64-unknown-linux-gnu`.
+#[derive(Debug)]
+pub struct Toolchain {
+    pub name: String,
+    dir: PathBuf,
+}
+
+#[derive(Deserialize)]
+struct Dated {
+    date: String,
+}
+
+impl Toolchain {
+    /// The channel the toolchain was installed from, with releases named by
+    /// their version counted as stable.
+    pub fn channel(&self) -> &str {
+        let channel = self.name.split('-').next().unwrap_or_default();
+        if channel.starts_with(|c: char| c.is_ascii_digit()) {
+            "stable"
+        } else {
+            channel
+        }
+    }
+
+    /// The manifest rustup saved when installing the toolchain, if it did.
+    pub fn manifest(&self) -> Result<Option<Vec<u8>>> {
+        let path = self.dir.join("lib/rustlib/multirust-channel-manifest.toml");
+        match std::fs::read(&path) {
+            Ok(content) => Ok(Some(content)),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+                Ok(None)
+            },
+            Err(error) => Err(error)
+                .with_context(|| format!("error reading {}", path.display())),
+        }
+    }
+
+    /// The date of the build that was installed, which undated names like
+    /// `nightly` don't tell.
+    pub fn date(&self) -> Result<Option<NaiveDate>> {
+        let content = match self.manifest()? {
+            Some(content) => content,
+            None => return Ok(None),
+        };
+        let dated: Dated = toml::from_slice(&content).with_context(|| {
+            format!("error parsing the manifest of {}", self.name)
+        })?;
+        Ok(dated.date.parse().ok())
+    }
+}
+
+/// The toolchains rustup installed, sorted by name, or none if rustup isn't
+/// installed.
+pub fn toolchains() -> Result<Vec<Toolchain>> {
+    let dir = match rustup_home() {
+        Some(home) => home.join("toolchains"),
+        None => return Ok(Vec::new()),
+    };
+    let entries = match std::fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+            return Ok(Vec::new())
+        },
+        Err(error) => {
+            return Err(error)
+                .with_context(|| format!("error reading {}", dir.display()))
+        },
+    };
+    let mut toolchains = Vec::new();
+    for entry in entries {
+        let entry = entry
+            .with_context(|| format!("error reading {}", dir.display()))?;
+        toolchains.push(Toolchain {
+            name: entry.file_name().to_string_lossy().into_owned(),
+            dir: entry.path(),
+        });
+    }
+    toolchains.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(toolchains)
+}
+
+/// The toolchains rustup installed by the channel and date of their build,
+/// read once so looking up many dated manifests doesn't read every installed
+/// toolchain's manifest each time.
+#[derive(Debug, Default)]
+pub struct InstalledBuilds(HashMap<(String, NaiveDate), Toolchain>);
+
+impl InstalledBuilds {
+    /// Reads the date of every installed toolchain.
+    pub fn read() -> Result<Self> {
+        let mut builds = HashMap::new();
+        for toolchain in toolchains()? {
+            if let Some(date) = toolchain.date()? {
+                builds
+                    .entry((toolchain.channel().to_owned(), date))
+                    .or_insert(toolchain);
+            }
+        }
+        Ok(InstalledBuilds(builds))
+    }
+
+    /// Finds the manifest rustup saved when installing the build of
+    /// `channel` published on `date`. Manifests of the latest build of a
+    /// channel are never read from rustup since an installed toolchain may be
+    /// out of date.
+    ///
+    /// Rustup rewrites the manifests it saves, so their content no longer
+    /// matches the published SHA-256. Rustup checked the download when
+    /// installing it.
+    pub fn manifest(
+        &self,
+        date: &str,
+        channel: &str,
+    ) -> Result<Option<Vec<u8>>> {
+        let date = match date.parse() {
+            Ok(date) => date,
+            Err(_) => return Ok(None),
+        };
+        match self.0.get(&(channel.to_owned(), date)) {
+            Some(toolchain) => toolchain.manifest(),
+            None => Ok(None),
+        }
+    }
+}
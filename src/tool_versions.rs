@@ -0,0 +1,185 @@
+//! Choosing pins by the version of the tools they ship rather than by date.
+
+use crate::{
+    find_package,
+    has_components,
+    make_toolchain_name,
+    manifests,
+    package_version,
+    toolchain_file::parse_version,
+    Manifest,
+    Query,
+    Requirements,
+    Session,
+};
+use anyhow::{anyhow, bail, Result};
+use std::{cmp::Ordering, fmt, str::FromStr};
+
+#[derive(Debug, Clone, Copy)]
+enum Comparison {
+    Less,
+    LessOrEqual,
+    Equal,
+    GreaterOrEqual,
+    Greater,
+}
+
+impl Comparison {
+    fn allows(self, ordering: Ordering) -> bool {
+        match self {
+            Comparison::Less => ordering == Ordering::Less,
+            Comparison::LessOrEqual => ordering != Ordering::Greater,
+            Comparison::Equal => ordering == Ordering::Equal,
+            Comparison::GreaterOrEqual => ordering != Ordering::Less,
+            Comparison::Greater => ordering == Ordering::Greater,
+        }
+    }
+
+    fn symbol(self) -> &'static str {
+        match self {
+            Comparison::Less => "<",
+            Comparison::LessOrEqual => "<=",
+            Comparison::Equal => "==",
+            Comparison::GreaterOrEqual => ">=",
+            Comparison::Greater => ">",
+        }
+    }
+}
+
+/// A requirement on the version of a component, like `clippy>=0.1.79`.
+#[derive(Debug, Clone)]
+pub struct ComponentVersion {
+    pub component: String,
+    comparison: Comparison,
+    version: Vec<u64>,
+}
+
+impl FromStr for ComponentVersion {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || anyhow!("expected a requirement like clippy>=0.1.79");
+        let index = s
+            .find(|c: char| matches!(c, '<' | '>' | '='))
+            .ok_or_else(invalid)?;
+        let (component, rest) = s.split_at(index);
+        let (comparison, version) = [
+            (">=", Comparison::GreaterOrEqual),
+            ("<=", Comparison::LessOrEqual),
+            ("==", Comparison::Equal),
+            (">", Comparison::Greater),
+            ("<", Comparison::Less),
+            ("=", Comparison::Equal),
+        ]
+        .iter()
+        .find_map(|&(prefix, comparison)| {
+            rest.strip_prefix(prefix)
+                .map(|version| (comparison, version))
+        })
+        .ok_or_else(invalid)?;
+        let (component, version) = (component.trim(), version.trim());
+        if component.is_empty()
+            || version.is_empty()
+            || !version.split('.').all(|part| part.parse::<u64>().is_ok())
+        {
+            return Err(invalid());
+        }
+        Ok(ComponentVersion {
+            component: component.to_owned(),
+            comparison,
+            version: parse_version(version),
+        })
+    }
+}
+
+impl fmt::Display for ComponentVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let version =
+            self.version.iter().map(u64::to_string).collect::<Vec<_>>();
+        write!(
+            f,
+            "{}{}{}",
+            self.component,
+            self.comparison.symbol(),
+            version.join(".")
+        )
+    }
+}
+
+impl ComponentVersion {
+    /// Whether the component is available for all of `targets` in `manifest`
+    /// with a version meeting the requirement.
+    pub fn is_satisfied_by(
+        &self,
+        manifest: &Manifest,
+        targets: &[&str],
+    ) -> bool {
+        if !has_components(
+            manifest,
+            std::slice::from_ref(&self.component),
+            targets,
+        ) {
+            return false;
+        }
+        find_package(manifest, &self.component)
+            .and_then(package_version)
+            .map_or(false, |version| {
+                self.comparison
+                    .allows(parse_version(version).cmp(&self.version))
+            })
+    }
+}
+
+pub fn run(
+    session: &Session,
+    query: &Query,
+    component: &str,
+    min_version: Option<&str>,
+) -> Result<()> {
+    let requirements = Requirements::new(session, query)?;
+    let components = [component.to_owned()];
+    let min_version = min_version.map(parse_version);
+
+    let mut found = false;
+    for manifest in manifests(
+        &session.fetcher,
+        &query.channel,
+        requirements.max_age,
+        &requirements.skip_dates,
+    )? {
+        let manifest = manifest?;
+        if !requirements.is_viable(&manifest)
+            || !has_components(&manifest, &components, &requirements.targets())
+        {
+            continue;
+        }
+        let version = match find_package(&manifest, component)
+            .and_then(package_version)
+        {
+            Some(version) => version,
+            None => continue,
+        };
+        if let Some(min_version) = &min_version {
+            if parse_version(version) < *min_version {
+                continue;
+            }
+        }
+
+        found = true;
+        println!(
+            "{} {} {}",
+            make_toolchain_name(&manifest, &query.channel, query.force_date),
+            component,
+            version
+        );
+    }
+
+    if !found {
+        bail!(
+            "no viable {} build in the search window ships a matching {}",
+            query.channel,
+            component
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,180 @@
+//! Reading the `rust-toolchain` files projects use to pin their toolchain.
+
+use crate::{get_rust_version, Manifest, Session};
+use anyhow::{bail, Context, Result};
+use chrono::NaiveDate;
+use regex::Regex;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+/// File names rustup looks for, in the order it prefers them.
+pub static FILE_NAMES: &[&str] = &["rust-toolchain", "rust-toolchain.toml"];
+
+#[derive(Debug, Deserialize)]
+struct ToolchainFile {
+    toolchain: ToolchainSection,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct ToolchainSection {
+    channel: Option<String>,
+    profile: Option<String>,
+    components: Vec<String>,
+    targets: Vec<String>,
+}
+
+/// What a toolchain file asks rustup to install.
+#[derive(Debug, Default)]
+pub struct Requested {
+    pub channel: Option<String>,
+    pub profile: Option<String>,
+    pub components: Vec<String>,
+    pub targets: Vec<String>,
+}
+
+/// Finds the toolchain file that applies to `dir`, searching parent
+/// directories the same way rustup does.
+pub fn find(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors().find_map(|dir| {
+        FILE_NAMES
+            .iter()
+            .map(|name| dir.join(name))
+            .find(|path| path.is_file())
+    })
+}
+
+/// Reads the pinned toolchain name from either the legacy single-line format
+/// or the TOML format.
+pub fn read_channel(path: &Path) -> Result<Option<String>> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("error reading {}", path.display()))?;
+    parse_channel(&content)
+        .with_context(|| format!("error parsing {}", path.display()))
+}
+
+/// Reads everything the toolchain file asks for. The legacy format only has
+/// the channel.
+pub fn read(path: &Path) -> Result<Requested> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("error reading {}", path.display()))?;
+    parse(&content).with_context(|| format!("error parsing {}", path.display()))
+}
+
+/// Replaces the pinned toolchain in the file at `path` with `name`, leaving
+/// the formatting, comments and other settings of the TOML format as they
+/// are.
+pub fn write_channel(session: &Session, path: &Path, name: &str) -> Result<()> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("error reading {}", path.display()))?;
+    let content = if is_legacy(&content) {
+        format!("{}\n", name)
+    } else {
+        let channel =
+            Regex::new(r#"(?m)^(\s*channel\s*=\s*)("[^"]*"|'[^']*')"#).unwrap();
+        let section = Regex::new(r#"(?m)^\s*\[toolchain\][^\n]*\n"#).unwrap();
+        if channel.is_match(&content) {
+            channel
+                .replace(&content, |captures: &regex::Captures| {
+                    format!("{}\"{}\"", &captures[1], name)
+                })
+                .into_owned()
+        } else if let Some(header) = section.find(&content) {
+            format!(
+                "{}channel = \"{}\"\n{}",
+                &content[..header.end()],
+                name,
+                &content[header.end()..]
+            )
+        } else {
+            bail!("{} has no [toolchain] section", path.display());
+        }
+    };
+    session.write_file(path, content)
+}
+
+/// Whether the file is in the legacy format, which only has the toolchain
+/// name.
+fn is_legacy(content: &str) -> bool {
+    !content
+        .trim()
+        .contains(|c: char| matches!(c, '\n' | '=' | '['))
+}
+
+fn parse_channel(content: &str) -> Result<Option<String>> {
+    Ok(parse(content)?.channel)
+}
+
+fn parse(content: &str) -> Result<Requested> {
+    if is_legacy(content) {
+        let trimmed = content.trim();
+        return Ok(Requested {
+            channel: Some(trimmed.to_owned()).filter(|name| !name.is_empty()),
+            ..Requested::default()
+        });
+    }
+    let file = toml::from_str::<ToolchainFile>(content)?;
+    Ok(Requested {
+        channel: file.toolchain.channel,
+        profile: file.toolchain.profile,
+        components: file.toolchain.components,
+        targets: file.toolchain.targets,
+    })
+}
+
+/// The toolchain a project is pinned to.
+pub struct Pin {
+    pub channel: String,
+    pub date: Option<NaiveDate>,
+    pub version: Option<String>,
+}
+
+impl Pin {
+    /// Parses a dated toolchain like `nightly-2019-05-04` or a stable
+    /// version like `1.34.1`. Unpinned channel names give `None`.
+    pub fn parse(name: &str) -> Option<Self> {
+        let dated = Regex::new(r#"^(stable|beta|nightly)-(\d{4}-\d{2}-\d{2})"#)
+            .unwrap();
+        if let Some(captures) = dated.captures(name) {
+            return Some(Pin {
+                channel: captures[1].to_owned(),
+                date: captures[2].parse().ok(),
+                version: None,
+            });
+        }
+        let version = Regex::new(r#"^\d+\.\d+(\.\d+)?$"#).unwrap();
+        if version.is_match(name) {
+            return Some(Pin {
+                channel: "stable".to_owned(),
+                date: None,
+                version: Some(name.to_owned()),
+            });
+        }
+        None
+    }
+
+    /// Whether `latest`, a build of the pin's channel, is newer than the
+    /// pinned build. Versions are compared only as far as the pin goes, so
+    /// `1.79` isn't behind `1.79.1`.
+    pub fn is_behind(&self, latest: &Manifest) -> bool {
+        match (self.date, &self.version) {
+            (Some(date), _) => latest.date > date,
+            (None, Some(version)) => {
+                let pinned = parse_version(version);
+                let mut newest = parse_version(
+                    &get_rust_version(latest).unwrap_or_default(),
+                );
+                newest.truncate(pinned.len());
+                pinned < newest
+            },
+            (None, None) => false,
+        }
+    }
+}
+
+pub fn parse_version(version: &str) -> Vec<u64> {
+    version
+        .split('.')
+        .map(|part| part.parse().unwrap_or(0))
+        .collect()
+}
@@ -0,0 +1,82 @@
+//! Translating the name of a binary into the component that ships it.
+
+use crate::{
+    find_package,
+    has_components,
+    make_toolchain_name,
+    select_manifest,
+    Query,
+    Requirements,
+    Session,
+};
+use anyhow::{bail, Result};
+
+/// Binaries installed by rustup proxies or by components, and the component
+/// each comes from.
+static BINARIES: &[(&str, &str)] = &[
+    ("cargo", "cargo"),
+    ("cargo-clippy", "clippy"),
+    ("cargo-fmt", "rustfmt"),
+    ("cargo-miri", "miri"),
+    ("clippy-driver", "clippy"),
+    ("llvm-ar", "llvm-tools"),
+    ("llvm-cov", "llvm-tools"),
+    ("llvm-nm", "llvm-tools"),
+    ("llvm-objcopy", "llvm-tools"),
+    ("llvm-objdump", "llvm-tools"),
+    ("llvm-profdata", "llvm-tools"),
+    ("llvm-readobj", "llvm-tools"),
+    ("llvm-size", "llvm-tools"),
+    ("llvm-strip", "llvm-tools"),
+    ("miri", "miri"),
+    ("rls", "rls"),
+    ("rust-analyzer", "rust-analyzer"),
+    ("rust-gdb", "rustc"),
+    ("rust-gdbgui", "rustc"),
+    ("rust-lld", "rustc"),
+    ("rust-lldb", "rustc"),
+    ("rustc", "rustc"),
+    ("rustdoc", "rustc"),
+    ("rustfmt", "rustfmt"),
+];
+
+pub fn run(session: &Session, query: &Query, binary: &str) -> Result<()> {
+    let binary = binary.trim_end_matches(".exe");
+    let component = match BINARIES.iter().find(|&&(name, _)| name == binary) {
+        Some(&(_, component)) => component,
+        None => bail!("don't know which component ships {}", binary),
+    };
+    println!("{} is shipped by the {} component", binary, component);
+
+    let requirements = Requirements::new(session, query)?;
+    let manifest = select_manifest(session, query, &requirements)?;
+    let toolchain =
+        make_toolchain_name(&manifest, &query.channel, query.force_date);
+    let profile = requirements.profile.name();
+
+    if find_package(&manifest, component).is_none() {
+        println!("{} doesn't have {}", toolchain, component);
+    } else if !has_components(
+        &manifest,
+        &[component.to_owned()],
+        &requirements.targets(),
+    ) {
+        println!(
+            "{} has {}, but not on all of the selected targets",
+            toolchain, component
+        );
+    } else if manifest.profiles.get(profile).map_or(false, |packages| {
+        packages.iter().any(|package| package == component)
+    }) {
+        println!(
+            "{} has {}, and the {} profile already installs it",
+            toolchain, component, profile
+        );
+    } else {
+        println!(
+            "{} has {}, require it with --add-component {}",
+            toolchain, component, component
+        );
+    }
+    Ok(())
+}